@@ -0,0 +1,90 @@
+//! A custom multi-child algorithm that completes after both of its children
+//! complete, discarding their values.
+
+#![feature(impl_trait_in_assoc_type)]
+
+use core::{convert::Infallible, marker::PhantomData, pin::Pin};
+
+use placid::prelude::*;
+use rxec_core::{
+    Receiver, Sender,
+    basic::{AllOf, BasicSender, FanIn, SenderExpr, SenderExprTo, State},
+    completion::FlagOr,
+    env::EmptyEnv,
+    list::split_first,
+    util::{map, sync_wait, value},
+};
+use tsum::{Sum, T, t};
+
+struct BothExpr<A, B>(PhantomData<(A, B)>);
+
+impl<A, B> SenderExpr for BothExpr<A, B>
+where
+    A: Sender,
+    B: Sender<Error = A::Error>,
+{
+    type Output = ();
+    type Error = A::Error;
+    type Stopped = FlagOr<A::Stopped, B::Stopped>;
+    type Attrs = EmptyEnv;
+    type Data = ();
+    type SubSenders = T![A, B];
+
+    fn attrs(_: &(), _: &T![A, B]) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<A, B, R> SenderExprTo<R> for BothExpr<A, B>
+where
+    A: Sender,
+    B: Sender<Error = A::Error>,
+    R: Receiver<(), A::Error>,
+{
+    // The children may complete concurrently, so the state is shared, and the
+    // last child to complete hands the outcome off to the receiver.
+    type State = FanIn<AllOf<(A::Output, (B::Output, ())), A::Error>, R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(_: (), _: &mut T![A, B], recv: R) -> Self::CreateState {
+        init::with(move || FanIn::new(2, AllOf::new(), recv))
+    }
+
+    // With multiple sub-senders, the state is only shared-borrowed.
+    fn complete(state: Pin<&State<Self, R>>, value: Sum![A::Output, B::Output]) {
+        if let Some(handoff) = state.state().get_ref().set(value) {
+            handoff.complete(drop);
+        }
+    }
+
+    fn error(state: Pin<&State<Self, R>>, error: Sum![A::Error, B::Error]) {
+        let error = match split_first(error) {
+            Ok(error) => error,
+            Err(rest) => rest.into_inner(),
+        };
+        if let Some(handoff) = state.state().get_ref().set_error(error) {
+            handoff.complete(drop);
+        }
+    }
+
+    fn stop(state: Pin<&State<Self, R>>) {
+        if let Some(handoff) = state.state().get_ref().set_stopped() {
+            handoff.complete(drop);
+        }
+    }
+}
+
+fn both<A, B>(a: A, b: B) -> BasicSender<BothExpr<A, B>>
+where
+    A: Sender,
+    B: Sender<Error = A::Error>,
+{
+    BasicSender::new((), t![a, b])
+}
+
+fn main() {
+    let a = map(value(1), |x| println!("a: {x}"));
+    let b = map(value("two"), |x| println!("b: {x}"));
+    sync_wait(both(a, b)).unwrap();
+}
@@ -0,0 +1,86 @@
+//! A custom single-child algorithm that inspects the value of its
+//! predecessor before passing it on.
+
+#![feature(impl_trait_in_assoc_type)]
+
+use core::{convert::Infallible, marker::PhantomData, pin::Pin};
+
+use placid::prelude::*;
+use rxec_core::{
+    Receiver, Sender,
+    basic::{BasicSender, SenderExpr, SenderExprTo, State},
+    env::{EnvOf, GetEnv},
+    util::{map, sync_wait, value},
+};
+use tsum::{Sum, T, t};
+
+struct InspectExpr<S, F>(PhantomData<(S, F)>);
+
+impl<S, F> SenderExpr for InspectExpr<S, F>
+where
+    S: Sender,
+    F: FnOnce(&S::Output),
+{
+    type Output = S::Output;
+    type Error = S::Error;
+    type Stopped = S::Stopped;
+    type Attrs = EnvOf<S>;
+    type Data = F;
+    type SubSenders = T![S];
+
+    fn attrs(_: &F, sub_senders: &T![S]) -> EnvOf<S> {
+        sub_senders.0.get_env()
+    }
+}
+
+struct InspectState<T>(Option<T>);
+
+impl<T> Unpin for InspectState<T> {}
+
+impl<S, F, R> SenderExprTo<R> for InspectExpr<S, F>
+where
+    S: Sender,
+    F: FnOnce(&S::Output),
+    R: Receiver<S::Output, S::Error>,
+{
+    type State = InspectState<(F, R)>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(data: F, _: &mut T![S], recv: R) -> Self::CreateState {
+        init::with(move || InspectState(Some((data, recv))))
+    }
+
+    // With a single sub-sender, the state is exclusively borrowed.
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let (func, recv) = state.state_mut().0.take().unwrap();
+        let value = value.into_inner();
+        func(&value);
+        recv.set(value);
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let (_, recv) = state.state_mut().0.take().unwrap();
+        recv.set_error(error.into_inner());
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        if let Some((_, recv)) = state.state_mut().0.take() {
+            recv.set_stopped();
+        }
+    }
+}
+
+fn inspect<S, F>(sender: S, func: F) -> BasicSender<InspectExpr<S, F>>
+where
+    S: Sender,
+    F: FnOnce(&S::Output),
+{
+    BasicSender::new(func, t![sender])
+}
+
+fn main() {
+    let sender = inspect(map(value(20), |x| x + 1), |x| println!("got {x}"));
+    let result = sync_wait(map(sender, |x| x * 2)).unwrap();
+    assert_eq!(result, 42);
+}
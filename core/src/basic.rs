@@ -1,3 +1,22 @@
+//! The framework for writing sender algorithms.
+//!
+//! An algorithm is described by a [`SenderExpr`], which declares the
+//! completions of the algorithm, the data it carries and the list of
+//! sub-senders it is composed of. [`BasicSender`] turns the description into a
+//! sender, whose operation state, [`BasicOperation`], connects every sub-sender
+//! in place to a [`BasicReceiver`] pointing back to the shared [`State`].
+//!
+//! The behavior of the algorithm is implemented by [`SenderExprTo`], whose
+//! methods are invoked on starting the operation and on every completion of
+//! the sub-senders. The state is handed out as a [`StateRef`], which is a
+//! `Pin<&mut State>` if there is at most one sub-sender, and a `Pin<&State>`
+//! otherwise, since the sub-senders may complete concurrently. Such shared
+//! states can be built upon [`FanIn`] and the other synchronized helpers in
+//! this module.
+//!
+//! See `examples/inspect.rs` and `examples/both.rs` for a single-child and a
+//! multi-child algorithm.
+
 use core::{
     fmt,
     marker::{PhantomData, PhantomPinned},
@@ -22,34 +41,63 @@ use tsum::{
 
 use crate::{
     basic::place::{ListPlaceRef, ListPlaceT},
+    completion::{Flag, No},
+    domain::{CustomizedFlag, CustomizedIn, Domain, TransformSender},
+    env::{EnvOf, GetDomain, GetEnv, Queryable, StopEnv},
     list::{
-        CountListT, IndexList, IndexListT, OperationStateList, PCons, PTerm, SenderList,
-        SenderOutputList, UIndex, USub, USubT,
+        CountListT, IndexList, IndexListT, OperationStateList, PCons, PTerm, SenderErrorList,
+        SenderList, SenderOutputList, UIndex, USub, USubT,
     },
-    traits::{ConnectOp, OperationState, Receiver, Sender, SenderOutput, SenderTo},
+    stop::InPlaceStopSource,
+    traits::{ConnectOp, OperationState, Receiver, Sender, SenderError, SenderOutput, SenderTo},
 };
 
 mod place;
-pub use self::place::ListPlace;
+mod shared;
+pub use self::{
+    place::{ListPlace, ListPlaceRef, ListPlaceT},
+    shared::{AllOf, Countdown, FanIn, Gather, Handoff, OnceSlot, SlotList, StopFanIn},
+};
 
+/// The description of a sender algorithm.
 pub trait SenderExpr: Sized {
     type Output;
+    type Error;
+    type Stopped: Flag;
+    type Attrs: Clone;
     type Data;
     type SubSenders: SenderList + ListPlace;
+
+    /// Whether domains may customize the algorithm, each opting in through
+    /// [`Customizes`](crate::domain::Customizes). Algorithms are not
+    /// customizable by default.
+    type Customizable: Flag = No;
+
+    /// Returns the attributes of the sender.
+    fn attrs(data: &Self::Data, sub_senders: &Self::SubSenders) -> Self::Attrs;
 }
 
-pub trait SenderExprTo<R>: SenderExpr {
+/// The behavior of a sender algorithm connected to the receiver `R`.
+pub trait SenderExprTo<R: GetEnv>: SenderExpr {
     type State;
-    type Error: fmt::Debug;
+    type ConnectError: fmt::Debug;
+
+    /// The environment of the receivers connected to the sub-senders. They
+    /// share the environment of `R` by default.
+    type SubEnv: DeriveEnv<EnvOf<R>, Self::State> = EnvOf<R>;
 
-    type CreateState: InitPin<Self::State, Error = Self::Error>;
+    type CreateState: InitPin<Self::State, Error = Self::ConnectError>;
 
+    /// Creates the state of the operation. The sub-senders are connected
+    /// afterwards, so they may be modified beforehand.
     fn create_state(
         data: Self::Data,
         sub_senders: &mut Self::SubSenders,
         receiver: R,
     ) -> Self::CreateState;
 
+    /// Starts the operation. The default implementation starts all the
+    /// sub-operations in order.
     fn start(state: StateRef<'_, Self, R>, subops: Pin<&mut ConnectAllOps<Self, R>>)
     where
         State<Self, R>: ConnectAll<Self, R>,
@@ -59,12 +107,54 @@ pub trait SenderExprTo<R>: SenderExpr {
         unsafe { subops.start_list_by_ref() };
     }
 
+    /// Called when a sub-sender completes with a value.
     fn complete(state: StateRef<'_, Self, R>, value: Sum<SenderOutputList<Self::SubSenders>>);
 
+    /// Called when a sub-sender completes with an error.
+    fn error(state: StateRef<'_, Self, R>, error: Sum<SenderErrorList<Self::SubSenders>>);
+
+    /// Called when a sub-sender completes with a stopped signal.
+    ///
+    /// It is not called if the receiver is merely dropped, which only happens
+    /// when the operation is torn down, e.g. dropped before it starts. The
+    /// state, and the outer receiver in it, are then dropped without
+    /// completion as well.
     fn stop(state: StateRef<'_, Self, R>) {
         let _ = state;
     }
 }
+
+/// An environment derived from the environment `E` of the outer receiver,
+/// e.g. one that answers the stop token of a stop source in the state `S`.
+pub trait DeriveEnv<E, S>: Clone {
+    /// Derives the environment once the state has been created in place.
+    ///
+    /// `state` stays valid as long as any of the receivers connected to the
+    /// sub-senders, which are the only holders of the derived environment.
+    fn derive(env: E, state: NonNull<S>) -> Self;
+}
+
+impl<E: Clone, S> DeriveEnv<E, S> for E {
+    fn derive(env: E, _: NonNull<S>) -> E {
+        env
+    }
+}
+
+/// States holding a stop source for their sub-senders, which observe it through
+/// a [`StopEnv`] as their [`SenderExprTo::SubEnv`].
+pub trait StopSourceState {
+    /// Points to the stop source in the state, without creating any reference.
+    fn stop_source(state: NonNull<Self>) -> NonNull<InPlaceStopSource>;
+}
+
+impl<E: Clone, S: StopSourceState> DeriveEnv<E, S> for StopEnv<E> {
+    fn derive(env: E, state: NonNull<S>) -> Self {
+        // SAFETY: The stop source lives in the state, which outlives all the
+        // sub-operations and thus the environments.
+        unsafe { StopEnv::new(env, S::stop_source(state)) }
+    }
+}
+
 pub type StatePlace<S, R> = ListPlaceT<<S as SenderExpr>::SubSenders, State<S, R>>;
 pub type StateRef<'a, S, R> = ListPlaceRef<'a, <S as SenderExpr>::SubSenders, State<S, R>>;
 
@@ -72,18 +162,26 @@ pub struct BasicReceiver<S, R, U: UIndex>
 where
     S: SenderExprTo<R, SubSenders: IndexList<U, Output: Sender>>,
     SenderOutputList<S::SubSenders>: repr::Split<SenderOutput<IndexListT<S::SubSenders, U>>, U>,
+    SenderErrorList<S::SubSenders>: repr::Split<SenderError<IndexListT<S::SubSenders, U>>, U>,
     U: UIndex,
+    R: GetEnv,
 {
     index: PhantomData<U>,
     // Effectively a StateRef<'state, S, R>.
     state: NonNull<StatePlace<S, R>>,
+    // Effectively a &'state S::SubEnv, type-erased so that the sub-operations
+    // can be connected without naming it.
+    env: NonNull<()>,
 }
 
 unsafe impl<S, R, U> Send for BasicReceiver<S, R, U>
 where
     S: SenderExprTo<R, SubSenders: IndexList<U, Output: Sender>>,
     SenderOutputList<S::SubSenders>: repr::Split<SenderOutput<IndexListT<S::SubSenders, U>>, U>,
+    SenderErrorList<S::SubSenders>: repr::Split<SenderError<IndexListT<S::SubSenders, U>>, U>,
     U: UIndex,
+    R: GetEnv,
+    S::SubEnv: Sync,
     for<'a> StateRef<'a, S, R>: Send,
 {
 }
@@ -92,16 +190,23 @@ unsafe impl<S, R, U> Sync for BasicReceiver<S, R, U>
 where
     S: SenderExprTo<R, SubSenders: IndexList<U, Output: Sender>>,
     SenderOutputList<S::SubSenders>: repr::Split<SenderOutput<IndexListT<S::SubSenders, U>>, U>,
+    SenderErrorList<S::SubSenders>: repr::Split<SenderError<IndexListT<S::SubSenders, U>>, U>,
     U: UIndex,
+    R: GetEnv,
+    S::SubEnv: Sync,
     for<'a> StateRef<'a, S, R>: Sync,
 {
 }
 
-impl<S, R, U> Receiver<SenderOutput<IndexListT<S::SubSenders, U>>> for BasicReceiver<S, R, U>
+impl<S, R, U>
+    Receiver<SenderOutput<IndexListT<S::SubSenders, U>>, SenderError<IndexListT<S::SubSenders, U>>>
+    for BasicReceiver<S, R, U>
 where
     S: SenderExprTo<R, SubSenders: IndexList<U, Output: Sender>>,
     SenderOutputList<S::SubSenders>: repr::Split<SenderOutput<IndexListT<S::SubSenders, U>>, U>,
+    SenderErrorList<S::SubSenders>: repr::Split<SenderError<IndexListT<S::SubSenders, U>>, U>,
     U: UIndex,
+    R: GetEnv,
 {
     fn set(self, value: SenderOutput<IndexListT<S::SubSenders, U>>) {
         S::complete(
@@ -114,17 +219,35 @@ where
             Sum::new(value),
         );
     }
+
+    fn set_error(self, error: SenderError<IndexListT<S::SubSenders, U>>) {
+        S::error(
+            // SAFETY: See the safety comment in `<Self as Receiver<T, E>>::set`.
+            unsafe { <S::SubSenders as ListPlace>::from_raw(self.state) },
+            Sum::new(error),
+        );
+    }
+
+    fn set_stopped(self) {
+        // SAFETY: See the safety comment in `<Self as Receiver<T, E>>::set`.
+        S::stop(unsafe { <S::SubSenders as ListPlace>::from_raw(self.state) });
+    }
 }
 
-impl<S, R, U> Drop for BasicReceiver<S, R, U>
+impl<S, R, U> GetEnv for BasicReceiver<S, R, U>
 where
     S: SenderExprTo<R, SubSenders: IndexList<U, Output: Sender>>,
     SenderOutputList<S::SubSenders>: repr::Split<SenderOutput<IndexListT<S::SubSenders, U>>, U>,
+    SenderErrorList<S::SubSenders>: repr::Split<SenderError<IndexListT<S::SubSenders, U>>, U>,
     U: UIndex,
+    R: GetEnv,
 {
-    fn drop(&mut self) {
-        // SAFETY: See the safety comment in `<Self as Receiver<T>>::set`.
-        S::stop(unsafe { <S::SubSenders as ListPlace>::from_raw(self.state) });
+    type Env = S::SubEnv;
+
+    fn get_env(&self) -> S::SubEnv {
+        // SAFETY: `env` points to the `env` field of the `BasicOperation` that created
+        // this struct, which is dropped after its `sub_ops` field, the same as `state`.
+        unsafe { self.env.cast::<S::SubEnv>().as_ref() }.clone()
     }
 }
 
@@ -133,6 +256,7 @@ where
 pub struct State<S, R>
 where
     S: SenderExprTo<R>,
+    R: GetEnv,
 {
     #[pin]
     _marker: PhantomPinned,
@@ -143,21 +267,25 @@ where
 pub trait ConnectList<A: ListPlace, SubSenders: SenderList, E, U: UIndex>: Sized {
     type OpList: OperationStateList;
 
+    /// `env` is a type-erased pointer to the environment of the sub-senders.
     fn connect_list(
         this: A::Ref<'_, Self>,
         sub_senders: SubSenders,
+        env: NonNull<()>,
     ) -> impl InitPin<Self::OpList, Error = E>;
 }
 
 impl<S, R, A: ListPlace, E: fmt::Debug> ConnectList<A, (), E, UTerm> for State<S, R>
 where
     S: SenderExprTo<R>,
+    R: GetEnv,
 {
     type OpList = PTerm;
 
     fn connect_list(
         _this: A::Ref<'_, Self>,
         _sub_senders: (),
+        _env: NonNull<()>,
     ) -> impl InitPin<Self::OpList, Error = E> {
         init_pin!(PTerm)
     }
@@ -166,7 +294,7 @@ where
 impl<S, R, T, E: fmt::Debug> ConnectList<(T, ()), (T, ()), E, UInt<UTerm>> for State<S, R>
 where
     S: SenderExprTo<R, SubSenders = (T, ())>,
-    R:,
+    R: GetEnv,
     // Head bounds
     T: SenderTo<BasicReceiver<S, R, UTerm>, ConnectError: Into<E>>,
 {
@@ -175,11 +303,13 @@ where
     fn connect_list<'a>(
         state: Pin<&mut Self>,
         sub_senders: (T, ()),
+        env: NonNull<()>,
     ) -> impl InitPin<Self::OpList, Error = E> {
         let head_receiver = BasicReceiver {
             index: PhantomData,
             // SAFETY: We don't move `state` after this.
             state: NonNull::from_mut(unsafe { Pin::into_inner_unchecked(state) }),
+            env,
         };
         let head_operation = sub_senders.0.connect(head_receiver);
         init_pin!(PCons(
@@ -202,6 +332,8 @@ where
     (T, (THead, TTail)): IndexList<USubT<CountListT<(T, (THead, TTail))>, UInt<U>>, Output = Head>,
     SenderOutputList<(T, (THead, TTail))>:
         repr::Split<SenderOutput<Head>, USubT<CountListT<(T, (THead, TTail))>, UInt<U>>>,
+    SenderErrorList<(T, (THead, TTail))>:
+        repr::Split<SenderError<Head>, USubT<CountListT<(T, (THead, TTail))>, UInt<U>>>,
     Head: SenderTo<
             BasicReceiver<S, R, USubT<CountListT<(T, (THead, TTail))>, UInt<U>>>,
             ConnectError: Into<E>,
@@ -210,6 +342,7 @@ where
     Tail: SenderList,
     Self: ConnectList<(T, (THead, TTail)), Tail, E, U>,
     // Other bounds
+    R: GetEnv,
     U: UIndex,
     E: fmt::Debug,
 {
@@ -221,40 +354,44 @@ where
     fn connect_list<'a>(
         state: Pin<&Self>,
         (head, tail): (Head, Tail),
+        env: NonNull<()>,
     ) -> impl InitPin<Self::OpList, Error = E> {
         let head_receiver = BasicReceiver {
             index: PhantomData,
             state: NonNull::from_ref(&*state),
+            env,
         };
         let head_operation = head.connect(head_receiver);
-        let tail_operations = Self::connect_list(state, tail);
+        let tail_operations = Self::connect_list(state, tail, env);
         init_pin!(PCons(head_operation.map_err(Into::into), tail_operations))
     }
 }
 
-pub trait ConnectAll<S: SenderExprTo<R>, R>:
-    ConnectList<S::SubSenders, S::SubSenders, S::Error, CountListT<S::SubSenders>>
+pub trait ConnectAll<S: SenderExprTo<R>, R: GetEnv>:
+    ConnectList<S::SubSenders, S::SubSenders, S::ConnectError, CountListT<S::SubSenders>>
 {
     type Operations: OperationStateList;
 
     fn connect_all(
         this: ListPlaceRef<'_, S::SubSenders, Self>,
         sub_senders: S::SubSenders,
-    ) -> impl InitPin<Self::Operations, Error = S::Error>;
+        env: NonNull<()>,
+    ) -> impl InitPin<Self::Operations, Error = S::ConnectError>;
 }
 impl<S, R, T> ConnectAll<S, R> for T
 where
     S: SenderExprTo<R>,
-    R:,
-    T: ConnectList<S::SubSenders, S::SubSenders, S::Error, CountListT<S::SubSenders>>,
+    R: GetEnv,
+    T: ConnectList<S::SubSenders, S::SubSenders, S::ConnectError, CountListT<S::SubSenders>>,
 {
     type Operations = T::OpList;
 
     fn connect_all(
         this: ListPlaceRef<'_, S::SubSenders, T>,
         sub_senders: S::SubSenders,
-    ) -> impl InitPin<Self::Operations, Error = S::Error> {
-        Self::connect_list(this, sub_senders)
+        env: NonNull<()>,
+    ) -> impl InitPin<Self::Operations, Error = S::ConnectError> {
+        Self::connect_list(this, sub_senders, env)
     }
 }
 
@@ -263,12 +400,13 @@ pub type ConnectAllOps<S, R> = <State<S, R> as ConnectAll<S, R>>::Operations;
 impl<S, R> State<S, R>
 where
     S: SenderExprTo<R>,
+    R: GetEnv,
 {
     pub(super) fn new(
         data: S::Data,
         sub_senders: &mut S::SubSenders,
         receiver: R,
-    ) -> impl InitPin<Self, Error = S::Error> {
+    ) -> impl InitPin<Self, Error = S::ConnectError> {
         init_pin!(State {
             _marker: PhantomPinned,
             state: S::create_state(data, sub_senders, receiver),
@@ -282,6 +420,11 @@ where
     pub fn state_mut(self: Pin<&mut Self>) -> Pin<&mut S::State> {
         self.project().state
     }
+
+    fn state_ptr(this: *mut Self) -> NonNull<S::State> {
+        // SAFETY: Only the address of the field is computed from the valid state.
+        unsafe { NonNull::new_unchecked(&raw mut (*this).state) }
+    }
 }
 
 #[derive(InitPin)]
@@ -289,6 +432,7 @@ where
 pub struct BasicOperation<S, R>
 where
     S: SenderExprTo<R>,
+    R: GetEnv,
     State<S, R>: ConnectAll<S, R>,
 {
     #[pin]
@@ -302,18 +446,22 @@ where
     // `state` itself must not be accessed since creation.
     #[pin]
     state: StatePlace<S, R>,
+    // `env` is also referenced by `sub_ops`, so it must come after `sub_ops` as
+    // well.
+    env: S::SubEnv,
 }
 
 impl<S, R> BasicOperation<S, R>
 where
     S: SenderExprTo<R>,
+    R: GetEnv,
     State<S, R>: ConnectAll<S, R>,
 {
     pub fn new(
         data: S::Data,
         mut sub_senders: S::SubSenders,
         receiver: R,
-    ) -> impl InitPin<Self, Error = S::Error> {
+    ) -> impl InitPin<Self, Error = S::ConnectError> {
         init::try_raw_pin(move |mut uninit: Uninit<Self>, slot| unsafe {
             // SAFETY: Here we are creating potential self-referential structs: Any
             // `BasicReceiver<S, R>` that is stored in `sub_ops` contains a pointer to
@@ -321,11 +469,13 @@ where
             //
             // However, this is safe as long as we never move the `BasicOperation` after
             // it has been pinned, and `state` outlives `sub_ops`, which means that
-            // `sub_ops` must be dropped before `state`.
+            // `sub_ops` must be dropped before `state`. The same applies to `env`.
 
             let ptr = uninit.as_mut_ptr();
             let state = &raw mut (*ptr).state;
             let sub_ops = &raw mut (*ptr).sub_ops;
+            let env = &raw mut (*ptr).env;
+            let outer_env = receiver.get_env();
             {
                 let mut subslot = ManuallyDrop::new(DroppingSlot::new());
                 let subslot_ref = DropSlot::new_unchecked(&mut subslot);
@@ -337,16 +487,21 @@ where
                     Err(err) => return Err(InitPinError::new(err.error, uninit, slot)),
                 }
             }
+            let state_ptr = State::state_ptr(<S::SubSenders as ListPlace>::as_ptr(state));
+            env.write(<S::SubEnv as DeriveEnv<_, _>>::derive(outer_env, state_ptr));
             {
                 let mut subslot = ManuallyDrop::new(DroppingSlot::new());
                 let subslot_ref = DropSlot::new_unchecked(&mut subslot);
 
                 let borrow = <S::SubSenders as ListPlace>::borrow(Pin::new_unchecked(&mut *state));
-                let ops_init = <State<S, R> as ConnectAll<S, R>>::connect_all(borrow, sub_senders);
+                let env_ptr = NonNull::new_unchecked(env).cast();
+                let ops_init =
+                    <State<S, R> as ConnectAll<S, R>>::connect_all(borrow, sub_senders, env_ptr);
                 match Uninit::from_raw(sub_ops).try_write_pin(ops_init, subslot_ref) {
                     Ok(p) => mem::forget(p),
                     Err(err) => {
                         state.drop_in_place();
+                        env.drop_in_place();
                         return Err(InitPinError::new(err.error, uninit, slot));
                     }
                 }
@@ -360,6 +515,7 @@ where
 unsafe impl<S, R> OperationState for BasicOperation<S, R>
 where
     S: SenderExprTo<R>,
+    R: GetEnv,
     State<S, R>: ConnectAll<S, R>,
     ConnectAllOps<S, R>: OperationStateList,
 {
@@ -382,6 +538,21 @@ where
     pub const fn new(data: S::Data, sub_senders: S::SubSenders) -> Self {
         Self { data, sub_senders }
     }
+
+    pub fn into_parts(self) -> (S::Data, S::SubSenders) {
+        (self.data, self.sub_senders)
+    }
+}
+
+impl<S> GetEnv for BasicSender<S>
+where
+    S: SenderExpr,
+{
+    type Env = S::Attrs;
+
+    fn get_env(&self) -> S::Attrs {
+        S::attrs(&self.data, &self.sub_senders)
+    }
 }
 
 impl<S> Sender for BasicSender<S>
@@ -389,18 +560,23 @@ where
     S: SenderExpr,
 {
     type Output = S::Output;
+    type Error = S::Error;
+    type Stopped = S::Stopped;
 }
 
-impl<S, R> SenderTo<R> for BasicSender<S>
+impl<S, D, R> SenderTo<R> for BasicSender<S>
 where
-    S: SenderExprTo<R>,
-    R: Receiver<S::Output>,
-    State<S, R>: ConnectAll<S, R>,
+    S: SenderExpr<Attrs: for<'a> Queryable<'a, GetDomain, Output = D>>,
+    D: Domain,
+    R: Receiver<S::Output, S::Error>,
+    S::Customizable: CustomizedIn<D, S>,
+    CustomizedFlag<D, S>: TransformSender<D, S, R>,
 {
-    type Operation = BasicOperation<S, R>;
-    type ConnectError = S::Error;
+    type Operation = <CustomizedFlag<D, S> as TransformSender<D, S, R>>::Operation;
+    type ConnectError = <CustomizedFlag<D, S> as TransformSender<D, S, R>>::ConnectError;
 
     fn connect(self, receiver: R) -> impl InitPin<Self::Operation, Error = Self::ConnectError> {
-        BasicOperation::new(self.data, self.sub_senders, receiver)
+        let domain = self.get_env().query(GetDomain);
+        <CustomizedFlag<D, S> as TransformSender<D, S, R>>::connect(domain, self, receiver)
     }
 }
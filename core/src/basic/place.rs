@@ -2,6 +2,7 @@ use core::{fmt, pin::Pin, ptr::NonNull};
 
 use placid::prelude::*;
 
+/// How the state is placed and shared among the sub-senders in the list.
 pub trait ListPlace {
     type Place<T>;
 
@@ -9,8 +10,15 @@ pub trait ListPlace {
 
     fn borrow<T>(place: Pin<&mut Self::Place<T>>) -> Self::Ref<'_, T>;
 
+    /// Downgrades the reference, for algorithms that are generic over the
+    /// number of sub-senders.
+    fn shared<'a, T>(r: Self::Ref<'a, T>) -> Pin<&'a T>;
+
     unsafe fn from_raw<'a, T>(ptr: NonNull<Self::Place<T>>) -> Self::Ref<'a, T>;
 
+    /// Points to the value in the place, without creating any reference.
+    fn as_ptr<T>(place: *mut Self::Place<T>) -> *mut T;
+
     fn init<T, I, M, E>(value: I) -> impl InitPin<Self::Place<T>, Error = E>
     where
         I: IntoInitPin<T, M, Error = E>,
@@ -28,10 +36,18 @@ impl ListPlace for () {
         place
     }
 
+    fn shared<'a, T>(r: Pin<&'a mut T>) -> Pin<&'a T> {
+        r.into_ref()
+    }
+
     unsafe fn from_raw<'a, T>(mut place: NonNull<T>) -> Pin<&'a mut T> {
         unsafe { Pin::new_unchecked(place.as_mut()) }
     }
 
+    fn as_ptr<T>(place: *mut T) -> *mut T {
+        place
+    }
+
     fn init<T, I, M, E>(value: I) -> impl InitPin<T, Error = E>
     where
         I: IntoInitPin<T, M, Error = E>,
@@ -50,10 +66,18 @@ impl<P> ListPlace for (P, ()) {
         place
     }
 
+    fn shared<'a, T>(r: Pin<&'a mut T>) -> Pin<&'a T> {
+        r.into_ref()
+    }
+
     unsafe fn from_raw<'a, T>(mut place: NonNull<T>) -> Pin<&'a mut T> {
         unsafe { Pin::new_unchecked(place.as_mut()) }
     }
 
+    fn as_ptr<T>(place: *mut T) -> *mut T {
+        place
+    }
+
     fn init<T, I, M, E>(value: I) -> impl InitPin<Self::Place<T>, Error = E>
     where
         I: IntoInitPin<T, M, Error = E>,
@@ -75,10 +99,18 @@ where
         place.into_ref()
     }
 
+    fn shared<'a, T>(r: Pin<&'a T>) -> Pin<&'a T> {
+        r
+    }
+
     unsafe fn from_raw<'a, T>(ptr: NonNull<Self::Place<T>>) -> Pin<&'a T> {
         unsafe { Pin::new_unchecked(ptr.as_ref()) }
     }
 
+    fn as_ptr<T>(place: *mut T) -> *mut T {
+        place
+    }
+
     fn init<T, I, M, E>(value: I) -> impl InitPin<Self::Place<T>, Error = E>
     where
         I: IntoInitPin<T, M, Error = E>,
@@ -0,0 +1,566 @@
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    mem::MaybeUninit,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering::*},
+};
+
+use tsum::{Sum, sum::repr::SumList};
+
+use super::StopSourceState;
+use crate::{
+    Receiver,
+    completion::Completion,
+    env::{EnvOf, GetEnv, QueryStopToken},
+    list::split_first,
+    stop::{EnvStopCallback, InPlaceStopSource, OnStop},
+};
+
+const EMPTY: u8 = 0;
+const BUSY: u8 = 1;
+const FULL: u8 = 2;
+const TAKEN: u8 = 3;
+
+/// A slot that can be filled once and taken once, possibly from different
+/// threads.
+pub struct OnceSlot<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: The value is only accessed by the thread that wins the state
+// transition.
+unsafe impl<T: Send> Send for OnceSlot<T> {}
+// SAFETY: Same as above.
+unsafe impl<T: Send> Sync for OnceSlot<T> {}
+
+impl<T> Default for OnceSlot<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for OnceSlot<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.state.load(Relaxed) {
+            EMPTY | BUSY => "empty",
+            FULL => "full",
+            _ => "taken",
+        };
+        f.debug_tuple("OnceSlot").field(&state).finish()
+    }
+}
+
+impl<T> OnceSlot<T> {
+    pub const fn new() -> Self {
+        OnceSlot {
+            state: AtomicU8::new(EMPTY),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    pub const fn full(value: T) -> Self {
+        OnceSlot {
+            state: AtomicU8::new(FULL),
+            value: UnsafeCell::new(MaybeUninit::new(value)),
+        }
+    }
+
+    /// Fills the slot, or gives the value back if the slot has ever been
+    /// filled.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        if self
+            .state
+            .compare_exchange(EMPTY, BUSY, Acquire, Relaxed)
+            .is_err()
+        {
+            return Err(value);
+        }
+        // SAFETY: We are the only one in the `BUSY` state.
+        unsafe { (*self.value.get()).write(value) };
+        self.state.store(FULL, Release);
+        Ok(())
+    }
+
+    /// Takes the value out of the slot, if it is filled and not yet taken.
+    pub fn take(&self) -> Option<T> {
+        self.state
+            .compare_exchange(FULL, TAKEN, Acquire, Relaxed)
+            .ok()?;
+        // SAFETY: The value is initialized in the `FULL` state, and we are the
+        // only one that left it.
+        Some(unsafe { (*self.value.get()).assume_init_read() })
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.state.load(Acquire) == FULL
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if *self.state.get_mut() != FULL {
+            return None;
+        }
+        // SAFETY: The value is initialized in the `FULL` state.
+        Some(unsafe { self.value.get_mut().assume_init_mut() })
+    }
+}
+
+impl<T> Drop for OnceSlot<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == FULL {
+            // SAFETY: The value is initialized in the `FULL` state.
+            unsafe { self.value.get_mut().assume_init_drop() }
+        }
+    }
+}
+
+/// A counter of the sub-operations that have not completed yet.
+#[derive(Debug)]
+pub struct Countdown(AtomicUsize);
+
+impl Countdown {
+    pub const fn new(count: usize) -> Self {
+        Countdown(AtomicUsize::new(count))
+    }
+
+    /// Marks one sub-operation as completed, and returns whether it was the
+    /// last one.
+    ///
+    /// Everything written by the other sub-operations before their arrival is
+    /// visible to the last one.
+    pub fn arrive(&self) -> bool {
+        self.0.fetch_sub(1, AcqRel) == 1
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.0.load(Acquire)
+    }
+}
+
+/// A type list of values that can be stored into per-child slots as the
+/// sub-senders complete.
+pub trait SlotList: SumList + Sized {
+    type Slots;
+    /// The list of the values that may have been stored, e.g. the errors of
+    /// the sub-senders that have failed.
+    type Stored;
+
+    const LEN: usize;
+
+    fn empty_slots() -> Self::Slots;
+
+    /// Stores the value into the slot of its variant.
+    ///
+    /// Returns `false` and drops the value if the slot has already been
+    /// filled.
+    fn store(slots: &Self::Slots, value: Sum<Self>) -> bool;
+
+    /// Takes all the values out of the slots, or `None` if any of them is
+    /// missing, in which case the values taken so far are dropped.
+    fn take_all(slots: &Self::Slots) -> Option<Self>;
+
+    /// Takes each value that has been stored out of its slot.
+    fn take_stored(slots: &Self::Slots) -> Self::Stored;
+}
+
+impl SlotList for () {
+    type Slots = ();
+    type Stored = ();
+
+    const LEN: usize = 0;
+
+    fn empty_slots() {}
+
+    fn store(_: &(), value: Sum<()>) -> bool {
+        value.unreachable()
+    }
+
+    fn take_all(_: &()) -> Option<()> {
+        Some(())
+    }
+
+    fn take_stored(_: &()) {}
+}
+
+impl<Head, Tail> SlotList for (Head, Tail)
+where
+    (Head, Tail): SumList,
+    Tail: SlotList,
+{
+    type Slots = (OnceSlot<Head>, Tail::Slots);
+    type Stored = (Option<Head>, Tail::Stored);
+
+    const LEN: usize = Tail::LEN + 1;
+
+    fn empty_slots() -> Self::Slots {
+        (OnceSlot::new(), Tail::empty_slots())
+    }
+
+    fn store(slots: &Self::Slots, value: Sum<Self>) -> bool {
+        match split_first(value) {
+            Ok(head) => slots.0.set(head).is_ok(),
+            Err(tail) => Tail::store(&slots.1, tail),
+        }
+    }
+
+    fn take_all(slots: &Self::Slots) -> Option<Self> {
+        let head = slots.0.take()?;
+        Some((head, Tail::take_all(&slots.1)?))
+    }
+
+    fn take_stored(slots: &Self::Slots) -> Self::Stored {
+        (slots.0.take(), Tail::take_stored(&slots.1))
+    }
+}
+
+/// The completion handed off to the last sub-operation of a [`FanIn`].
+#[derive(Debug)]
+pub enum Handoff<L, E, R> {
+    Value(L, R),
+    Error(E, R),
+    Stopped(R),
+}
+
+impl<L, E, R> Handoff<L, E, R> {
+    /// Completes the receiver, mapping the collected values with `func`.
+    pub fn complete<T, F>(self, func: F)
+    where
+        F: FnOnce(L) -> T,
+        R: Receiver<T, E>,
+    {
+        match self {
+            Handoff::Value(values, recv) => recv.set(func(values)),
+            Handoff::Error(error, recv) => recv.set_error(error),
+            Handoff::Stopped(recv) => recv.set_stopped(),
+        }
+    }
+}
+
+/// Gathers the completions of the sub-senders of a [`FanIn`] into the
+/// completion of the whole.
+///
+/// The sub-senders may complete concurrently, so it is only shared-borrowed.
+pub trait Gather {
+    type Value;
+    type Error;
+
+    /// Takes the completion of the whole, once every sub-sender has arrived.
+    fn take(&self) -> Completion<Self::Value, Self::Error>;
+}
+
+/// Gathers the values of all the sub-senders. The first error takes
+/// precedence over stopped signals, which take precedence over values.
+pub struct AllOf<L: SlotList, E> {
+    values: L::Slots,
+    error: OnceSlot<E>,
+    stopped: AtomicBool,
+}
+
+impl<L: SlotList, E> Default for AllOf<L, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L: SlotList, E> AllOf<L, E> {
+    pub fn new() -> Self {
+        AllOf {
+            values: L::empty_slots(),
+            error: OnceSlot::new(),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    pub fn set(&self, value: Sum<L>) {
+        assert!(
+            L::store(&self.values, value),
+            "the sub-sender completed twice"
+        );
+    }
+
+    /// Records the error if it is the first one.
+    pub fn set_error(&self, error: E) {
+        let _ = self.error.set(error);
+    }
+
+    pub fn set_stopped(&self) {
+        self.stopped.store(true, Release);
+    }
+
+    /// Whether any sub-sender has completed with an error or a stopped
+    /// signal, i.e. whether the others are still worth waiting for.
+    pub fn is_failed(&self) -> bool {
+        self.error.is_full() || self.stopped.load(Acquire)
+    }
+}
+
+impl<L: SlotList, E> Gather for AllOf<L, E> {
+    type Value = L;
+    type Error = E;
+
+    fn take(&self) -> Completion<L, E> {
+        if let Some(error) = self.error.take() {
+            Completion::Error(error)
+        } else if self.stopped.load(Acquire) {
+            Completion::Stopped
+        } else {
+            let values = L::take_all(&self.values).expect("all the sub-senders have completed");
+            Completion::Value(values)
+        }
+    }
+}
+
+/// The shared state of an algorithm that waits for all of its sub-senders.
+///
+/// Every sub-sender reports its completion to the [`Gather`] and arrives
+/// exactly once, and the last one to arrive gets the receiver together with
+/// the gathered completion.
+///
+/// It only needs a shared reference, so it fits as the state of multi-child
+/// [`SenderExprTo`](super::SenderExprTo)s.
+pub struct FanIn<G, R> {
+    remaining: Countdown,
+    gather: G,
+    receiver: OnceSlot<R>,
+}
+
+impl<G: Gather, R> FanIn<G, R> {
+    /// Waits for `count` arrivals, usually one for each sub-sender, and one
+    /// more for the starter if it still touches the state after starting them.
+    pub fn new(count: usize, gather: G, receiver: R) -> Self {
+        FanIn {
+            remaining: Countdown::new(count),
+            gather,
+            receiver: OnceSlot::full(receiver),
+        }
+    }
+
+    pub fn gather(&self) -> &G {
+        &self.gather
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.remaining.remaining()
+    }
+
+    /// Hands off the receiver right away if there is no arrival to wait for,
+    /// which is meant to be called on starting the operation.
+    pub fn hand_off_empty(&self) -> Option<Handoff<G::Value, G::Error, R>> {
+        if self.remaining() != 0 {
+            return None;
+        }
+        self.hand_off()
+    }
+
+    /// Marks one arrival, and hands off the receiver if it was the last one.
+    pub fn arrive(&self) -> Option<Handoff<G::Value, G::Error, R>> {
+        if !self.remaining.arrive() {
+            return None;
+        }
+        self.hand_off()
+    }
+
+    fn hand_off(&self) -> Option<Handoff<G::Value, G::Error, R>> {
+        let receiver = self.receiver.take()?;
+        Some(match self.gather.take() {
+            Completion::Value(value) => Handoff::Value(value, receiver),
+            Completion::Error(error) => Handoff::Error(error, receiver),
+            Completion::Stopped => Handoff::Stopped(receiver),
+        })
+    }
+}
+
+impl<L: SlotList, E, R> FanIn<AllOf<L, E>, R> {
+    pub fn set(&self, value: Sum<L>) -> Option<Handoff<L, E, R>> {
+        self.gather.set(value);
+        self.arrive()
+    }
+
+    pub fn set_error(&self, error: E) -> Option<Handoff<L, E, R>> {
+        self.gather.set_error(error);
+        self.arrive()
+    }
+
+    pub fn set_stopped(&self) -> Option<Handoff<L, E, R>> {
+        self.gather.set_stopped();
+        self.arrive()
+    }
+}
+
+/// A [`FanIn`] with a stop source of its own, for algorithms that stop their
+/// sub-operations on their own. It also forwards the stop requests of the
+/// outer receiver to them.
+///
+/// A forwarding in progress holds back the [`Handoff`] of the last arrival, so
+/// that the callback no longer touches the state once the receiver is
+/// completed. The callback itself stays registered until the state is dropped,
+/// since it must not be dropped from within itself.
+pub struct StopFanIn<G: Gather, R: GetEnv<Env: QueryStopToken>> {
+    // Dropped first, since it refers to the other fields.
+    callback: EnvStopCallback<EnvOf<R>, ForwardStop<G, R>>,
+    source: InPlaceStopSource,
+    env: OnceSlot<EnvOf<R>>,
+    // Set by either the callback or the last arrival, whichever comes first.
+    claimed: AtomicBool,
+    // The callback and the last arrival, once the callback has claimed.
+    finished: Countdown,
+    handoff: OnceSlot<Handoff<G::Value, G::Error, R>>,
+    complete: fn(Handoff<G::Value, G::Error, R>),
+    fan_in: FanIn<G, R>,
+}
+
+/// The stop callback of a [`StopFanIn`].
+struct ForwardStop<G: Gather, R: GetEnv<Env: QueryStopToken>>(NonNull<StopFanIn<G, R>>);
+
+// SAFETY: It is only created by `StopFanIn::start`, which requires the
+// completion to be `Send`, and the state to be pinned.
+unsafe impl<G: Gather, R: GetEnv<Env: QueryStopToken>> Send for ForwardStop<G, R> {}
+
+impl<G: Gather, R: GetEnv<Env: QueryStopToken>> OnStop for ForwardStop<G, R> {
+    fn on_stop(self) {
+        // SAFETY: The callback is dropped along with the state.
+        unsafe { self.0.as_ref() }.forward();
+    }
+}
+
+impl<G: Gather, R: GetEnv<Env: QueryStopToken>> StopSourceState for StopFanIn<G, R> {
+    fn stop_source(state: NonNull<Self>) -> NonNull<InPlaceStopSource> {
+        // SAFETY: Only the address of the field is computed from the valid state.
+        unsafe { NonNull::new_unchecked(&raw mut (*state.as_ptr()).source) }
+    }
+}
+
+impl<G, R> StopFanIn<G, R>
+where
+    G: Gather<Value: Send, Error: Send>,
+    R: GetEnv<Env: QueryStopToken + Send> + Send,
+{
+    /// Waits for `count` arrivals like [`FanIn::new`], and completes the
+    /// handoff of the last one with `complete`.
+    pub fn new(
+        count: usize,
+        gather: G,
+        receiver: R,
+        complete: fn(Handoff<G::Value, G::Error, R>),
+    ) -> Self {
+        StopFanIn {
+            callback: EnvStopCallback::new(),
+            source: InPlaceStopSource::new(),
+            env: OnceSlot::full(receiver.get_env()),
+            claimed: AtomicBool::new(false),
+            finished: Countdown::new(2),
+            handoff: OnceSlot::new(),
+            complete,
+            fan_in: FanIn::new(count, gather, receiver),
+        }
+    }
+
+    /// Starts forwarding, which is meant to be called on starting the
+    /// operation, before any sub-operation.
+    ///
+    /// # Safety
+    ///
+    /// `self` must be pinned.
+    pub unsafe fn start(&self) {
+        let Some(env) = self.env.take() else {
+            return;
+        };
+        let forward = ForwardStop(NonNull::from_ref(self));
+        // SAFETY: The environment is taken only once, before any sub-operation
+        // starts, and the callback is dropped along with `self`.
+        unsafe { self.callback.register(env, forward) };
+    }
+}
+
+impl<G: Gather, R: GetEnv<Env: QueryStopToken>> StopFanIn<G, R> {
+    pub fn gather(&self) -> &G {
+        self.fan_in.gather()
+    }
+
+    pub fn source(&self) -> &InPlaceStopSource {
+        &self.source
+    }
+
+    /// Completes the receiver right away if there is no arrival to wait for,
+    /// like [`FanIn::hand_off_empty`].
+    pub fn finish_empty(&self) {
+        if let Some(handoff) = self.fan_in.hand_off_empty() {
+            self.finish(handoff);
+        }
+    }
+
+    /// Marks one arrival, after which the state may be gone.
+    pub fn arrive(&self) {
+        if let Some(handoff) = self.fan_in.arrive() {
+            self.finish(handoff);
+        }
+    }
+
+    fn forward(&self) {
+        if self.claimed.swap(true, AcqRel) {
+            return;
+        }
+        self.source.request_stop();
+        self.settle();
+    }
+
+    /// Completes the handoff of the last arrival, or leaves it to the callback
+    /// if a stop is being forwarded.
+    fn finish(&self, handoff: Handoff<G::Value, G::Error, R>) {
+        if !self.claimed.swap(true, AcqRel) {
+            // The callback has not forwarded any stop, and never will.
+            (self.complete)(handoff);
+            return;
+        }
+        let _ = self.handoff.set(handoff);
+        self.settle();
+    }
+
+    fn settle(&self) {
+        if !self.finished.arrive() {
+            return;
+        }
+        let handoff = self.handoff.take().expect("the fan-in has handed off");
+        (self.complete)(handoff);
+    }
+}
+
+impl<L: SlotList, E, R: GetEnv<Env: QueryStopToken>> StopFanIn<AllOf<L, E>, R> {
+    pub fn set(&self, value: Sum<L>) {
+        self.gather().set(value);
+        self.arrive();
+    }
+
+    pub fn set_error(&self, error: E) {
+        self.gather().set_error(error);
+        self.arrive();
+    }
+
+    pub fn set_stopped(&self) {
+        self.gather().set_stopped();
+        self.arrive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Values = (u32, (&'static str, ()));
+
+    #[test]
+    fn last_one_completes() {
+        let fan_in = FanIn::<AllOf<Values, ()>, _>::new(2, AllOf::new(), ());
+        assert!(fan_in.set(Sum::new("two")).is_none());
+        match fan_in.set(Sum::new(1)) {
+            Some(Handoff::Value((1, ("two", ())), ())) => {}
+            other => panic!("unexpected handoff: {other:?}"),
+        }
+
+        let fan_in = FanIn::<AllOf<Values, u8>, _>::new(2, AllOf::new(), ());
+        assert!(fan_in.set_stopped().is_none());
+        assert!(fan_in.gather().is_failed());
+        assert!(matches!(fan_in.set_error(7), Some(Handoff::Error(7, ()))));
+    }
+}
@@ -0,0 +1,70 @@
+use core::convert::Infallible;
+
+use crate::traits::{Receiver, Sender};
+
+/// A type-level boolean.
+pub trait Flag {
+    const VALUE: bool;
+
+    type Or<F: Flag>: Flag;
+    type And<F: Flag>: Flag;
+}
+pub type FlagOr<A, B> = <A as Flag>::Or<B>;
+pub type FlagAnd<A, B> = <A as Flag>::And<B>;
+
+pub struct Yes;
+
+impl Flag for Yes {
+    const VALUE: bool = true;
+
+    type Or<F: Flag> = Yes;
+    type And<F: Flag> = F;
+}
+
+pub struct No;
+
+impl Flag for No {
+    const VALUE: bool = false;
+
+    type Or<F: Flag> = F;
+    type And<F: Flag> = No;
+}
+
+/// A completion of a sender reified into a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Completion<T, E> {
+    Value(T),
+    Error(E),
+    Stopped,
+}
+
+impl<T, E> Completion<T, E> {
+    /// Completes the receiver with the reified completion.
+    pub fn deliver<R: Receiver<T, E>>(self, receiver: R) {
+        match self {
+            Completion::Value(value) => receiver.set(value),
+            Completion::Error(error) => receiver.set_error(error),
+            Completion::Stopped => receiver.set_stopped(),
+        }
+    }
+}
+
+pub type SenderStopped<S> = <S as Sender>::Stopped;
+
+/// A sender that may only complete with a value.
+///
+/// ```rust
+/// fn assert_infallible<S: rxec_core::completion::InfallibleSender>(_: S) {}
+///
+/// assert_infallible(rxec_core::util::map(rxec_core::util::value(1), |i| i + 1));
+/// ```
+///
+/// ```rust,compile_fail
+/// fn assert_infallible<S: rxec_core::completion::InfallibleSender>(_: S) {}
+///
+/// assert_infallible(rxec_core::util::async_(async { 1 }));
+/// ```
+pub trait InfallibleSender = Sender<Error = Infallible, Stopped = No>;
+
+/// A sender that never completes with a stopped signal.
+pub trait UnstoppableSender = Sender<Stopped = No>;
@@ -0,0 +1,235 @@
+use placid::init::InitPin;
+
+use crate::{
+    OperationState, Receiver, Sender, SenderTo,
+    basic::{BasicOperation, BasicSender, ConnectAll, SenderExpr, SenderExprTo, State},
+    completion::{Flag, No, Yes},
+};
+
+/// An execution context that may provide its own implementations of
+/// algorithms, e.g. a thread pool that fuses chains of work.
+///
+/// A domain customizes an algorithm by implementing [`Customizes`] for it with
+/// the flag set to [`Yes`], and [`Transform`] to provide the implementation.
+pub trait Domain: Clone {}
+
+/// The domain of senders that don't complete on any specific scheduler.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DefaultDomain;
+
+impl Domain for DefaultDomain {}
+
+impl<E> Customizes<E> for DefaultDomain {}
+
+/// Whether the domain customizes the algorithm `E`, which must be
+/// [customizable](SenderExpr::Customizable).
+///
+/// A customizable algorithm that completes in a domain requires the domain to
+/// implement this trait for it, even if only to keep the default of [`No`].
+/// Third-party algorithms opt in the same way as [`map`](crate::util::map) and
+/// [`bulk`](crate::util::bulk) do.
+pub trait Customizes<E>: Domain {
+    type Flag: Flag = No;
+}
+
+/// Looks up whether the domain `D` customizes the algorithm `E`, which is only
+/// the case if `E` is customizable and `D` opts in through [`Customizes`].
+///
+/// It is implemented for [`SenderExpr::Customizable`], so that the domains need
+/// not know about the algorithms that are not customizable.
+pub trait CustomizedIn<D, E>: Flag {
+    type Flag: Flag;
+}
+
+impl<D, E> CustomizedIn<D, E> for No {
+    type Flag = No;
+}
+
+impl<D: Customizes<E>, E> CustomizedIn<D, E> for Yes {
+    type Flag = D::Flag;
+}
+
+/// Whether the domain `D` customizes the algorithm `E`.
+pub type CustomizedFlag<D, E> = <<E as SenderExpr>::Customizable as CustomizedIn<D, E>>::Flag;
+
+/// Customizes the algorithm `E` for the domain.
+///
+/// The transformed sender is connected in place of the algorithm. It must not
+/// be the same algorithm customized by the domain again, which would never
+/// finish connecting.
+pub trait Transform<E: SenderExpr>: Customizes<E> {
+    type Sender: Sender<Output = E::Output, Error = E::Error>;
+
+    fn transform(&self, sender: BasicSender<E>) -> Self::Sender;
+}
+
+/// Connects the algorithm `E` in the domain `D`, transformed by the domain if
+/// the flag is [`Yes`], and as it is otherwise.
+///
+/// It is dispatched on [`CustomizedFlag`] by the [`SenderTo`] implementation of
+/// [`BasicSender`], where the domain is queried from the attributes of the
+/// sender.
+pub trait TransformSender<D, E: SenderExpr, R>: Flag {
+    type Operation: OperationState;
+    type ConnectError: core::fmt::Debug;
+
+    fn connect(
+        domain: D,
+        sender: BasicSender<E>,
+        receiver: R,
+    ) -> impl InitPin<Self::Operation, Error = Self::ConnectError>;
+}
+
+impl<D, E, R> TransformSender<D, E, R> for No
+where
+    E: SenderExprTo<R>,
+    R: Receiver<E::Output, E::Error>,
+    State<E, R>: ConnectAll<E, R>,
+{
+    type Operation = BasicOperation<E, R>;
+    type ConnectError = E::ConnectError;
+
+    fn connect(
+        _: D,
+        sender: BasicSender<E>,
+        receiver: R,
+    ) -> impl InitPin<Self::Operation, Error = Self::ConnectError> {
+        let (data, sub_senders) = sender.into_parts();
+        BasicOperation::new(data, sub_senders, receiver)
+    }
+}
+
+impl<D, E, R> TransformSender<D, E, R> for Yes
+where
+    D: Transform<E, Sender: SenderTo<R>>,
+    E: SenderExpr,
+    R: Receiver<E::Output, E::Error>,
+{
+    type Operation = <D::Sender as SenderTo<R>>::Operation;
+    type ConnectError = <D::Sender as SenderTo<R>>::ConnectError;
+
+    fn connect(
+        domain: D,
+        sender: BasicSender<E>,
+        receiver: R,
+    ) -> impl InitPin<Self::Operation, Error = Self::ConnectError> {
+        domain.transform(sender).connect(receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use core::{
+        convert::Infallible,
+        pin::Pin,
+        sync::atomic::{AtomicUsize, Ordering::SeqCst},
+    };
+
+    use placid::prelude::*;
+
+    use super::*;
+    use crate::{Receiver, Scheduler, basic::*, env::SchedulerAttrs, util::*};
+
+    static FUSED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct PoolScheduler;
+
+    #[derive(Debug, Clone, Copy, Default)]
+    struct PoolDomain;
+
+    impl Domain for PoolDomain {}
+
+    impl<S, F> Customizes<MapExpr<S, F>> for PoolDomain {}
+
+    impl<S, F> Customizes<BulkExpr<S, F>> for PoolDomain {
+        type Flag = Yes;
+    }
+
+    // Fuses the loop into a single continuation.
+    impl<S, F> Transform<BulkExpr<S, F>> for PoolDomain
+    where
+        S: Sender,
+        F: FnMut(usize, &mut S::Output) + 'static,
+    {
+        type Sender = Map<S, Box<dyn FnOnce(S::Output) -> S::Output>>;
+
+        fn transform(&self, sender: Bulk<S, F>) -> Self::Sender {
+            let ((shape, mut func), (sender, ())) = sender.into_parts();
+            let fused: Box<dyn FnOnce(S::Output) -> S::Output> = Box::new(move |mut value| {
+                FUSED.fetch_add(1, SeqCst);
+                (0..shape).for_each(|index| func(index, &mut value));
+                value
+            });
+            map(sender, fused)
+        }
+    }
+
+    struct PoolTaskExpr;
+
+    impl SenderExpr for PoolTaskExpr {
+        type Output = ();
+        type Error = Infallible;
+        type Stopped = No;
+        type Attrs = SchedulerAttrs<PoolScheduler>;
+        type Data = ();
+        type SubSenders = ();
+
+        fn attrs(_: &(), _: &()) -> Self::Attrs {
+            SchedulerAttrs(PoolScheduler)
+        }
+    }
+
+    struct PoolTaskState<R>(Option<R>);
+
+    impl<R> Unpin for PoolTaskState<R> {}
+
+    impl<R: Receiver<()>> SenderExprTo<R> for PoolTaskExpr {
+        type State = PoolTaskState<R>;
+        type ConnectError = Infallible;
+        type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+        fn create_state(_: (), _: &mut (), recv: R) -> Self::CreateState {
+            init::value(PoolTaskState(Some(recv)))
+        }
+
+        fn start(state: Pin<&mut State<Self, R>>, _: Pin<&mut ConnectAllOps<Self, R>>)
+        where
+            State<Self, R>: ConnectAll<Self, R>,
+        {
+            state.state_mut().0.take().unwrap().set(());
+        }
+
+        fn complete(_: Pin<&mut State<Self, R>>, value: tsum::Sum<()>) {
+            value.unreachable();
+        }
+
+        fn error(_: Pin<&mut State<Self, R>>, error: tsum::Sum<()>) {
+            error.unreachable();
+        }
+    }
+
+    impl Scheduler for PoolScheduler {
+        type Task = BasicSender<PoolTaskExpr>;
+        type Domain = PoolDomain;
+
+        fn schedule(&self) -> Self::Task {
+            BasicSender::new((), ())
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn customized_bulk() {
+        let work = map(PoolScheduler.schedule(), |()| 0);
+        let s = bulk(work, 4, |index, sum: &mut usize| *sum += index);
+        assert_eq!(sync_wait(s).unwrap(), 6);
+        assert_eq!(FUSED.load(SeqCst), 1);
+
+        // Senders that don't complete on the pool are left as they are.
+        let s = bulk(value(0), 4, |index, sum: &mut usize| *sum += index);
+        assert_eq!(sync_wait(s).unwrap(), 6);
+        assert_eq!(FUSED.load(SeqCst), 1);
+    }
+}
@@ -0,0 +1,191 @@
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    ptr::NonNull,
+};
+
+use crate::{
+    Scheduler,
+    domain::{DefaultDomain, Domain},
+    stop::{InPlaceStopSource, InPlaceStopToken, NeverStopToken, StopToken},
+};
+
+/// Types that expose an environment, e.g. receivers, or senders for their
+/// attributes.
+pub trait GetEnv {
+    type Env: Clone;
+
+    fn get_env(&self) -> Self::Env;
+}
+pub type EnvOf<R> = <R as GetEnv>::Env;
+
+/// An environment that can answer the query `Q`.
+///
+/// The answer may borrow the environment for `'a`, e.g. the stop token of a
+/// [`StopEnv`].
+pub trait Queryable<'a, Q> {
+    type Output;
+
+    fn query(&'a self, query: Q) -> Self::Output;
+}
+pub type QueryOutput<'a, E, Q> = <E as Queryable<'a, Q>>::Output;
+
+/// Queries the scheduler that the operation is running on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct GetScheduler;
+
+/// Queries the stop token that applies to the operation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct GetStopToken;
+
+/// Environments that answer a stop token, which the operations observe.
+pub trait QueryStopToken = for<'a> Queryable<'a, GetStopToken, Output: StopToken>;
+
+/// Queries the allocator that the operation should allocate with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct GetAllocator;
+
+/// Queries the scheduler that a sender completes its value channel on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct GetCompletionScheduler;
+
+/// Queries the domain that a sender completes in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct GetDomain;
+
+/// The global allocator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Global;
+
+unsafe impl GlobalAlloc for Global {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: Forwarded from the caller.
+        unsafe { alloc::alloc::alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: Forwarded from the caller.
+        unsafe { alloc::alloc::dealloc(ptr, layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: Forwarded from the caller.
+        unsafe { alloc::alloc::alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: Forwarded from the caller.
+        unsafe { alloc::alloc::realloc(ptr, layout, new_size) }
+    }
+}
+
+/// The environment of receivers that have nothing to tell.
+///
+/// It answers the default stop token and allocator, but no scheduler.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct EmptyEnv;
+
+impl<'a> Queryable<'a, GetStopToken> for EmptyEnv {
+    type Output = NeverStopToken;
+
+    fn query(&'a self, _: GetStopToken) -> NeverStopToken {
+        NeverStopToken
+    }
+}
+
+impl<'a> Queryable<'a, GetAllocator> for EmptyEnv {
+    type Output = Global;
+
+    fn query(&'a self, _: GetAllocator) -> Global {
+        Global
+    }
+}
+
+impl<'a> Queryable<'a, GetDomain> for EmptyEnv {
+    type Output = DefaultDomain;
+
+    fn query(&'a self, _: GetDomain) -> DefaultDomain {
+        DefaultDomain
+    }
+}
+
+/// The attributes of senders that complete on the scheduler `S`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SchedulerAttrs<S>(pub S);
+
+impl<'a, S: Clone> Queryable<'a, GetCompletionScheduler> for SchedulerAttrs<S> {
+    type Output = S;
+
+    fn query(&'a self, _: GetCompletionScheduler) -> S {
+        self.0.clone()
+    }
+}
+
+impl<'a, S: Scheduler> Queryable<'a, GetDomain> for SchedulerAttrs<S> {
+    type Output = S::Domain;
+
+    fn query(&'a self, _: GetDomain) -> S::Domain {
+        self.0.domain()
+    }
+}
+
+/// The environment of the sub-operations of an algorithm that stops them on
+/// its own, e.g. [`when_any`](crate::util::when_any).
+///
+/// It answers the stop token of an [`InPlaceStopSource`] in the operation
+/// state, and forwards the other standard queries to the outer environment.
+#[derive(Debug, Clone, Copy)]
+pub struct StopEnv<E> {
+    env: E,
+    source: NonNull<InPlaceStopSource>,
+}
+
+// SAFETY: The stop source is synchronized.
+unsafe impl<E: Send> Send for StopEnv<E> {}
+// SAFETY: Same as above.
+unsafe impl<E: Sync> Sync for StopEnv<E> {}
+
+impl<E> StopEnv<E> {
+    /// # Safety
+    ///
+    /// The source must outlive the environment and all the copies of it.
+    pub const unsafe fn new(env: E, source: NonNull<InPlaceStopSource>) -> Self {
+        StopEnv { env, source }
+    }
+
+    pub const fn inner(&self) -> &E {
+        &self.env
+    }
+}
+
+impl<'a, E> Queryable<'a, GetStopToken> for StopEnv<E> {
+    type Output = InPlaceStopToken<'a>;
+
+    fn query(&'a self, _: GetStopToken) -> InPlaceStopToken<'a> {
+        // SAFETY: The source outlives the environment, which the token borrows.
+        unsafe { InPlaceStopToken::from_raw(self.source) }
+    }
+}
+
+impl<'a, E: Queryable<'a, GetScheduler>> Queryable<'a, GetScheduler> for StopEnv<E> {
+    type Output = E::Output;
+
+    fn query(&'a self, query: GetScheduler) -> E::Output {
+        self.env.query(query)
+    }
+}
+
+impl<'a, E: Queryable<'a, GetAllocator>> Queryable<'a, GetAllocator> for StopEnv<E> {
+    type Output = E::Output;
+
+    fn query(&'a self, query: GetAllocator) -> E::Output {
+        self.env.query(query)
+    }
+}
+
+impl<'a, E: Queryable<'a, GetDomain>> Queryable<'a, GetDomain> for StopEnv<E> {
+    type Output = E::Output;
+
+    fn query(&'a self, query: GetDomain) -> E::Output {
+        self.env.query(query)
+    }
+}
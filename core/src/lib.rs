@@ -1,17 +1,24 @@
 #![no_std]
+#![feature(associated_type_defaults)]
 #![feature(derive_coerce_pointee)]
 #![feature(impl_trait_in_assoc_type)]
 #![feature(trait_alias)]
 
 extern crate alloc;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "std"))]
 extern crate std;
 
-mod list;
+pub mod completion;
+pub mod domain;
+pub mod env;
+pub mod list;
+pub mod stop;
 mod traits;
-pub use self::traits::{OperationState, Receiver, ReceiverFrom, Scheduler, Sender, SenderTo};
+pub use self::traits::{
+    ConnectOp, OperationState, Receiver, ReceiverFrom, Scheduler, Sender, SenderError,
+    SenderOutput, SenderTo,
+};
 
-
-mod basic;
+pub mod basic;
 pub mod util;
@@ -2,12 +2,19 @@ use core::pin::Pin;
 
 use pin_project::pin_project;
 use placid::prelude::*;
-use tsum::sum::{
-    index::{UInt, UTerm},
-    repr::SumList,
+use tsum::{
+    Sum,
+    sum::{
+        index::{UInt, UTerm},
+        repr::SumList,
+    },
 };
 
-use crate::traits::Sender;
+use crate::{
+    basic::{OnceSlot, SlotList},
+    completion::{Flag, FlagOr, No},
+    traits::Sender,
+};
 
 pub trait UIndex = tsum::sum::index::Index;
 
@@ -25,15 +32,21 @@ impl<U: USub<V>, V: UIndex> USub<UInt<V>> for UInt<U> {
 
 pub trait CountList {
     type Count: UIndex;
+
+    const LEN: usize;
 }
 pub type CountListT<L> = <L as CountList>::Count;
 
 impl CountList for () {
     type Count = UTerm;
+
+    const LEN: usize = 0;
 }
 
 impl<Head, Tail: CountList> CountList for (Head, Tail) {
     type Count = UInt<Tail::Count>;
+
+    const LEN: usize = Tail::LEN + 1;
 }
 
 pub trait IndexList<U: UIndex>: CountList {
@@ -49,6 +62,90 @@ impl<Head, Tail: IndexList<U>, U: UIndex> IndexList<UInt<U>> for (Head, Tail) {
     type Output = Tail::Output;
 }
 
+/// Splits the first variant off a sum over a type list.
+///
+/// This is how multi-child [`SenderExprTo`]s find out which sub-sender has
+/// completed, by peeling the sum one variant at a time.
+///
+/// [`SenderExprTo`]: crate::basic::SenderExprTo
+pub fn split_first<Head, Tail>(sum: Sum<(Head, Tail)>) -> Result<Head, Sum<Tail>>
+where
+    (Head, Tail): SumList,
+    Tail: SumList,
+{
+    sum.split::<Head, UTerm>()
+}
+
+/// A type list whose elements are all `T`, so that a sum over it is told apart
+/// by the position of its variant only.
+pub trait UniformList<T>: SumList {
+    /// Returns the position of the variant in the list with its value.
+    fn into_indexed(sum: Sum<Self>) -> (usize, T);
+}
+
+impl<T> UniformList<T> for () {
+    fn into_indexed(sum: Sum<()>) -> (usize, T) {
+        sum.unreachable()
+    }
+}
+
+impl<T, Tail> UniformList<T> for (T, Tail)
+where
+    (T, Tail): SumList,
+    Tail: UniformList<T>,
+{
+    fn into_indexed(sum: Sum<Self>) -> (usize, T) {
+        match split_first(sum) {
+            Ok(value) => (0, value),
+            Err(tail) => {
+                let (index, value) = Tail::into_indexed(tail);
+                (index + 1, value)
+            }
+        }
+    }
+}
+
+/// A type list whose elements are all `Result`s with the same error type `E`.
+pub trait ResultList<E>: SumList {
+    /// The list of the `Ok` types.
+    type Oks: SlotList;
+
+    /// Stores the `Ok` value of the variant into its slot, or returns the `Err`
+    /// value.
+    ///
+    /// Returns `Ok(false)` and drops the value if the slot has already been
+    /// filled.
+    fn store_ok(slots: &<Self::Oks as SlotList>::Slots, value: Sum<Self>) -> Result<bool, E>;
+}
+
+impl<E> ResultList<E> for () {
+    type Oks = ();
+
+    fn store_ok(_: &(), value: Sum<()>) -> Result<bool, E> {
+        value.unreachable()
+    }
+}
+
+impl<T, E, Tail> ResultList<E> for (Result<T, E>, Tail)
+where
+    (Result<T, E>, Tail): SumList,
+    (T, Tail::Oks): SumList,
+    Tail: ResultList<E>,
+{
+    type Oks = (T, Tail::Oks);
+
+    fn store_ok(
+        slots: &(OnceSlot<T>, <Tail::Oks as SlotList>::Slots),
+        value: Sum<Self>,
+    ) -> Result<bool, E> {
+        match split_first(value) {
+            Ok(Ok(value)) => Ok(slots.0.set(value).is_ok()),
+            Ok(Err(error)) => Err(error),
+            Err(tail) => Tail::store_ok(&slots.1, tail),
+        }
+    }
+}
+
 pub trait PinnedList {
     type TupleList: CountList;
 
@@ -80,11 +177,18 @@ impl<Head, Tail: PinnedList> PinnedList for PCons<Head, Tail> {
 
 pub trait SenderList: CountList {
     type OutputList: SumList;
+    type ErrorList: SumList;
+    /// Whether any of the senders may complete with a stopped signal.
+    type Stopped: Flag;
 }
 pub type SenderOutputList<L> = <L as SenderList>::OutputList;
+pub type SenderErrorList<L> = <L as SenderList>::ErrorList;
+pub type SenderStoppedList<L> = <L as SenderList>::Stopped;
 
 impl SenderList for () {
     type OutputList = ();
+    type ErrorList = ();
+    type Stopped = No;
 }
 
 impl<Head, Tail> SenderList for (Head, Tail)
@@ -93,6 +197,8 @@ where
     Tail: SenderList,
 {
     type OutputList = (Head::Output, Tail::OutputList);
+    type ErrorList = (Head::Error, Tail::ErrorList);
+    type Stopped = FlagOr<Head::Stopped, Tail::Stopped>;
 }
 
 pub trait OperationStateList: PinnedList {
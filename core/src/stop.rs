@@ -0,0 +1,599 @@
+use core::{
+    cell::{Cell, UnsafeCell},
+    convert::Infallible,
+    marker::{PhantomData, PhantomPinned},
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, Ordering::*},
+};
+
+use placid::{
+    pin::{DropSlot, DroppingSlot},
+    prelude::*,
+};
+use spin::{Mutex, MutexGuard};
+
+use crate::env::{GetStopToken, QueryOutput, QueryStopToken};
+
+/// A callback invoked on a stop request, e.g. a closure.
+///
+/// Naming a type of its own, unlike a closure, lets the callback be stored in
+/// place in an operation state.
+pub trait OnStop: Send {
+    fn on_stop(self);
+}
+
+impl<F: FnOnce() + Send> OnStop for F {
+    fn on_stop(self) {
+        self()
+    }
+}
+
+pub trait StopToken: Clone {
+    type Callback<F: OnStop>: Send;
+
+    fn stop_requested(&self) -> bool;
+
+    fn stop_possible(&self) -> bool;
+
+    /// Registers a callback that is invoked when a stop is requested.
+    ///
+    /// If a stop has already been requested, the callback is invoked during
+    /// the initialization. Dropping the callback object deregisters it.
+    fn register<F>(&self, f: F) -> impl InitPin<Self::Callback<F>, Error = Infallible>
+    where
+        F: OnStop;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct NeverStopToken;
+
+pub struct NeverStopCallback<F>(PhantomData<F>);
+
+impl StopToken for NeverStopToken {
+    type Callback<F: OnStop> = NeverStopCallback<F>;
+
+    fn stop_requested(&self) -> bool {
+        false
+    }
+
+    fn stop_possible(&self) -> bool {
+        false
+    }
+
+    fn register<F>(&self, f: F) -> impl InitPin<Self::Callback<F>, Error = Infallible>
+    where
+        F: OnStop,
+    {
+        drop(f);
+        init::value(NeverStopCallback(PhantomData))
+    }
+}
+
+const LINKED: u8 = 0;
+const RUNNING: u8 = 1;
+const DONE: u8 = 2;
+// Not linked in any list yet.
+const IDLE: u8 = 3;
+
+struct Links {
+    prev: Option<NonNull<CallbackNode>>,
+    next: Option<NonNull<CallbackNode>>,
+}
+
+/// An intrusive node of [`Callbacks`], which lives in place in its owner.
+pub(crate) struct CallbackNode {
+    // Guarded by the lock of the list.
+    links: UnsafeCell<Links>,
+    state: AtomicU8,
+    // Points to a flag on the stack of the notifying thread while running.
+    removed: AtomicPtr<Cell<bool>>,
+    // The notifying thread while running. Guarded by the lock of the list.
+    #[cfg(feature = "std")]
+    notifier: UnsafeCell<Option<std::thread::ThreadId>>,
+    execute: unsafe fn(NonNull<CallbackNode>),
+}
+
+impl CallbackNode {
+    pub(crate) const fn new(execute: unsafe fn(NonNull<CallbackNode>)) -> Self {
+        CallbackNode {
+            links: UnsafeCell::new(Links { prev: None, next: None }),
+            state: AtomicU8::new(IDLE),
+            removed: AtomicPtr::new(ptr::null_mut()),
+            #[cfg(feature = "std")]
+            notifier: UnsafeCell::new(None),
+            execute,
+        }
+    }
+}
+
+pub(crate) struct CallbackList {
+    head: Option<NonNull<CallbackNode>>,
+    tail: Option<NonNull<CallbackNode>>,
+}
+
+// SAFETY: The nodes are only accessed with the lock of the list held, or by
+// the thread that owns them.
+unsafe impl Send for CallbackList {}
+
+impl CallbackList {
+    /// Links the node at the back of the list.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be valid and pinned until it is deregistered, and not linked
+    /// in any list.
+    pub(crate) unsafe fn push_back(&mut self, node: NonNull<CallbackNode>) {
+        unsafe {
+            node.as_ref().state.store(LINKED, Relaxed);
+            let links = &mut *node.as_ref().links.get();
+            links.prev = self.tail;
+            links.next = None;
+            match self.tail {
+                Some(tail) => (*tail.as_ref().links.get()).next = Some(node),
+                None => self.head = Some(node),
+            }
+        }
+        self.tail = Some(node);
+    }
+
+    /// # Safety
+    ///
+    /// `node` must be valid and linked in this list.
+    unsafe fn remove(&mut self, node: NonNull<CallbackNode>) {
+        unsafe {
+            let links = &*node.as_ref().links.get();
+            match links.prev {
+                Some(prev) => (*prev.as_ref().links.get()).next = links.next,
+                None => self.head = links.next,
+            }
+            match links.next {
+                Some(next) => (*next.as_ref().links.get()).prev = links.prev,
+                None => self.tail = links.prev,
+            }
+        }
+    }
+
+    fn pop_front(&mut self) -> Option<NonNull<CallbackNode>> {
+        let head = self.head?;
+        // SAFETY: `head` is linked in this list.
+        unsafe { self.remove(head) };
+        Some(head)
+    }
+}
+
+/// A list of intrusive callbacks, each of which is executed at most once by
+/// the thread that notifies it.
+pub(crate) struct Callbacks {
+    list: Mutex<CallbackList>,
+}
+
+impl Callbacks {
+    pub(crate) const fn new() -> Self {
+        Callbacks {
+            list: Mutex::new(CallbackList { head: None, tail: None }),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, CallbackList> {
+        self.list.lock()
+    }
+
+    /// Executes all the linked callbacks one by one on the current thread,
+    /// without holding the lock while running them.
+    pub(crate) fn execute_all(&self) {
+        self.execute_while(|| true);
+    }
+
+    /// Like [`execute_all`](Self::execute_all), but stops as soon as `cond`
+    /// no longer holds, which is checked with the lock held before each
+    /// callback.
+    pub(crate) fn execute_while(&self, cond: impl Fn() -> bool) {
+        loop {
+            let mut list = self.list.lock();
+            if !cond() {
+                break;
+            }
+            let Some(node) = list.pop_front() else {
+                break;
+            };
+            let removed = Cell::new(false);
+            // SAFETY: `node` is valid until it is deregistered, which requires the lock
+            // we are holding.
+            unsafe {
+                let node_ref = node.as_ref();
+                node_ref.state.store(RUNNING, Relaxed);
+                node_ref
+                    .removed
+                    .store(ptr::from_ref(&removed).cast_mut(), Relaxed);
+                #[cfg(feature = "std")]
+                {
+                    *node_ref.notifier.get() = Some(std::thread::current().id());
+                }
+            }
+            drop(list);
+
+            // SAFETY: `node` is in `RUNNING` state, so its owner waits for us in its
+            // destructor unless it is dropped by the callback itself, in which case
+            // `removed` is set and `node` must not be touched anymore.
+            unsafe {
+                (node.as_ref().execute)(node);
+                if !removed.get() {
+                    node.as_ref().state.store(DONE, Release);
+                }
+            }
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The lock of the list must be held.
+    #[cfg(feature = "std")]
+    unsafe fn is_notifying_thread(node: &CallbackNode) -> bool {
+        // SAFETY: The caller holds the lock.
+        unsafe { *node.notifier.get() == Some(std::thread::current().id()) }
+    }
+
+    #[cfg(not(feature = "std"))]
+    unsafe fn is_notifying_thread(_: &CallbackNode) -> bool {
+        false
+    }
+
+    /// Unlinks the node, or waits for it to finish running on another thread.
+    ///
+    /// Returns `true` if the node was linked, and thus will never be executed.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be valid, and linked in this list if it is linked at all.
+    pub(crate) unsafe fn deregister(&self, node: &CallbackNode) -> bool {
+        let mut list = self.list.lock();
+        match node.state.load(Acquire) {
+            LINKED => {
+                // SAFETY: The node is linked in the list.
+                unsafe { list.remove(NonNull::from_ref(node)) };
+                node.state.store(IDLE, Relaxed);
+                true
+            }
+            RUNNING => {
+                // SAFETY: We hold the lock.
+                let reentrant = unsafe { Self::is_notifying_thread(node) };
+                drop(list);
+                if reentrant {
+                    // Dropped from within the callback.
+                    let removed = node.removed.load(Relaxed);
+                    // SAFETY: `removed` lives on the stack of `execute_all`, which is
+                    // still running on this thread.
+                    unsafe { (*removed).set(true) };
+                } else {
+                    while node.state.load(Acquire) != DONE {
+                        relax();
+                    }
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Backs off while waiting for another thread, yielding it the processor if
+/// possible.
+pub(crate) fn relax() {
+    #[cfg(feature = "std")]
+    std::thread::yield_now();
+    #[cfg(not(feature = "std"))]
+    core::hint::spin_loop();
+}
+
+/// A stop source that lives in place, e.g. in a pinned operation state.
+///
+/// Tokens borrow the source, so it cannot be moved or dropped while any
+/// callback is still registered.
+pub struct InPlaceStopSource {
+    requested: AtomicBool,
+    callbacks: Callbacks,
+}
+
+impl Default for InPlaceStopSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InPlaceStopSource {
+    pub const fn new() -> Self {
+        InPlaceStopSource {
+            requested: AtomicBool::new(false),
+            callbacks: Callbacks::new(),
+        }
+    }
+
+    pub fn token(&self) -> InPlaceStopToken<'_> {
+        InPlaceStopToken { source: self }
+    }
+
+    pub fn stop_requested(&self) -> bool {
+        self.requested.load(Acquire)
+    }
+
+    /// Requests a stop and invokes all the registered callbacks on the current
+    /// thread.
+    ///
+    /// Returns `false` if a stop has already been requested.
+    pub fn request_stop(&self) -> bool {
+        if self.requested.swap(true, AcqRel) {
+            return false;
+        }
+        self.callbacks.execute_all();
+        true
+    }
+
+    /// # Safety
+    ///
+    /// `node` must be valid and pinned until it is deregistered.
+    unsafe fn register(&self, node: NonNull<CallbackNode>) {
+        let mut list = self.callbacks.lock();
+        if !self.requested.load(Acquire) {
+            // SAFETY: `node` is not yet linked.
+            unsafe { list.push_back(node) };
+            return;
+        }
+        drop(list);
+
+        // SAFETY: The stop has been requested, so invoke the callback inline.
+        unsafe {
+            node.as_ref().state.store(DONE, Relaxed);
+            (node.as_ref().execute)(node);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct InPlaceStopToken<'a> {
+    source: &'a InPlaceStopSource,
+}
+
+impl<'a> InPlaceStopToken<'a> {
+    /// Creates a token from a raw pointer to a stop source.
+    ///
+    /// # Safety
+    ///
+    /// The source must outlive the returned token and all the callbacks
+    /// registered through it.
+    pub unsafe fn from_raw(source: NonNull<InPlaceStopSource>) -> Self {
+        InPlaceStopToken {
+            // SAFETY: The caller guarantees the validity of `source`.
+            source: unsafe { source.as_ref() },
+        }
+    }
+}
+
+impl<'a> StopToken for InPlaceStopToken<'a> {
+    type Callback<F: OnStop> = InPlaceStopCallback<'a, F>;
+
+    fn stop_requested(&self) -> bool {
+        self.source.stop_requested()
+    }
+
+    fn stop_possible(&self) -> bool {
+        true
+    }
+
+    fn register<F>(&self, f: F) -> impl InitPin<Self::Callback<F>, Error = Infallible>
+    where
+        F: OnStop,
+    {
+        InPlaceStopCallback::new(*self, f)
+    }
+}
+
+/// A callback registered to an [`InPlaceStopSource`].
+///
+/// Dropping the callback deregisters it. If the callback is running on
+/// another thread, the destructor waits for it to finish.
+///
+/// Without the `std` feature, the callback must not be dropped from within
+/// itself, since the destructor cannot tell the notifying thread from others
+/// and would wait forever.
+#[repr(C)]
+pub struct InPlaceStopCallback<'a, F: OnStop> {
+    // `node` must be the first field so that it can be cast back to `Self`.
+    node: CallbackNode,
+    source: &'a InPlaceStopSource,
+    callback: UnsafeCell<ManuallyDrop<F>>,
+    _marker: PhantomPinned,
+}
+
+// SAFETY: `F` is only accessed by the thread that invokes or drops it.
+unsafe impl<F: OnStop> Send for InPlaceStopCallback<'_, F> {}
+// SAFETY: `F` is never accessed through a shared reference.
+unsafe impl<F: OnStop> Sync for InPlaceStopCallback<'_, F> {}
+
+impl<'a, F: OnStop> InPlaceStopCallback<'a, F> {
+    pub fn new(token: InPlaceStopToken<'a>, f: F) -> impl InitPin<Self, Error = Infallible> {
+        init::try_raw_pin(move |mut uninit: Uninit<Self>, slot| unsafe {
+            // SAFETY: The node is registered after it is written in place, and
+            // deregistered in the destructor, which is guaranteed to run by the
+            // pinning guarantee.
+            let ptr = uninit.as_mut_ptr();
+            ptr.write(InPlaceStopCallback {
+                node: CallbackNode::new(Self::execute),
+                source: token.source,
+                callback: UnsafeCell::new(ManuallyDrop::new(f)),
+                _marker: PhantomPinned,
+            });
+            token.source.register(NonNull::new_unchecked(ptr).cast());
+
+            Ok(uninit.assume_init_pin(slot))
+        })
+    }
+
+    /// # Safety
+    ///
+    /// `node` must point to the `node` field of a valid `Self`, and this
+    /// function must be called at most once for it.
+    unsafe fn execute(node: NonNull<CallbackNode>) {
+        let this = node.cast::<Self>().as_ptr();
+        // SAFETY: The callback is taken out only once.
+        let f = unsafe { ManuallyDrop::take(&mut *(*this).callback.get()) };
+        f.on_stop();
+    }
+}
+
+impl<F: OnStop> Drop for InPlaceStopCallback<'_, F> {
+    fn drop(&mut self) {
+        // SAFETY: The node is linked in the list of the source if at all.
+        if unsafe { self.source.callbacks.deregister(&self.node) } {
+            // SAFETY: The callback is never invoked.
+            unsafe { ManuallyDrop::drop(self.callback.get_mut()) };
+        }
+    }
+}
+
+/// The callback type of the stop token of `E`, with the borrow of `E` erased.
+type ErasedCallback<E, F> = <QueryOutput<'static, E, GetStopToken> as StopToken>::Callback<F>;
+
+/// A callback registered to the stop token of an environment, which lives in
+/// place and keeps the environment alongside since the token may borrow it.
+///
+/// It is empty until registered, so that it fits in an operation state before
+/// the operation starts. Dropping it deregisters the callback, with the same
+/// caveats as [`InPlaceStopCallback`].
+pub struct EnvStopCallback<E: QueryStopToken, F: OnStop> {
+    // Borrows `env`, so it is dropped first. Both are initialized once
+    // registered.
+    callback: UnsafeCell<MaybeUninit<ErasedCallback<E, F>>>,
+    env: UnsafeCell<MaybeUninit<E>>,
+    registered: UnsafeCell<bool>,
+    _marker: PhantomPinned,
+}
+
+// SAFETY: The callback is `Send`, and the environment is owned.
+unsafe impl<E: QueryStopToken + Send, F: OnStop> Send for EnvStopCallback<E, F> {}
+// SAFETY: Nothing is accessed through a shared reference but on registering,
+// which is never concurrent.
+unsafe impl<E: QueryStopToken, F: OnStop> Sync for EnvStopCallback<E, F> {}
+
+impl<E: QueryStopToken, F: OnStop> Default for EnvStopCallback<E, F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: QueryStopToken, F: OnStop> EnvStopCallback<E, F> {
+    pub const fn new() -> Self {
+        EnvStopCallback {
+            callback: UnsafeCell::new(MaybeUninit::uninit()),
+            env: UnsafeCell::new(MaybeUninit::uninit()),
+            registered: UnsafeCell::new(false),
+            _marker: PhantomPinned,
+        }
+    }
+
+    /// Registers `f` to the stop token of `env`. If a stop has already been
+    /// requested, `f` is invoked before this function returns.
+    ///
+    /// # Safety
+    ///
+    /// - `self` must be pinned, and this function must be called at most once
+    ///   for it, not concurrently with any other access.
+    /// - `self` must be dropped before anything that `f` refers to.
+    pub unsafe fn register(&self, env: E, f: F) {
+        // SAFETY: We are the only one accessing the fields, which are pinned. The
+        // environment is dropped only after the callback.
+        unsafe {
+            let env = (*self.env.get()).write(env);
+            register_in_place(env, self.callback.get().cast(), f);
+            *self.registered.get() = true;
+        }
+    }
+}
+
+/// # Safety
+///
+/// `slot` must be valid to write and pinned, and the callback written there
+/// must be dropped before `env`.
+unsafe fn register_in_place<'a, E: QueryStopToken, F: OnStop>(
+    env: &'a E,
+    slot: *mut ErasedCallback<E, F>,
+    f: F,
+) {
+    let token = env.query(GetStopToken);
+    // Only the lifetime differs, which the caller upholds.
+    let slot = slot.cast::<<QueryOutput<'a, E, GetStopToken> as StopToken>::Callback<F>>();
+    // SAFETY: The callback is initialized in place and never moved, and the caller
+    // drops it.
+    unsafe {
+        let mut drop_slot = ManuallyDrop::new(DroppingSlot::new());
+        let drop_slot_ref = DropSlot::new_unchecked(&mut drop_slot);
+        match Uninit::from_raw(slot).try_write_pin(token.register(f), drop_slot_ref) {
+            Ok(p) => mem::forget(p),
+            Err(err) => match err.error {},
+        }
+    }
+}
+
+impl<E: QueryStopToken, F: OnStop> Drop for EnvStopCallback<E, F> {
+    fn drop(&mut self) {
+        if !*self.registered.get_mut() {
+            return;
+        }
+        // SAFETY: Both are initialized, and the callback is dropped before the
+        // environment that it borrows.
+        unsafe {
+            self.callback.get_mut().assume_init_drop();
+            self.env.get_mut().assume_init_drop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicBool, Ordering::SeqCst};
+
+    use placid::pown;
+
+    use super::*;
+    use crate::env::{EmptyEnv, StopEnv};
+
+    #[test]
+    fn callbacks() {
+        let source = InPlaceStopSource::new();
+        let token = source.token();
+        let (a, b, c) = (
+            AtomicBool::new(false),
+            AtomicBool::new(false),
+            AtomicBool::new(false),
+        );
+        {
+            let _registered = pown!(token.register(|| a.store(true, SeqCst)));
+            let deregistered = pown!(token.register(|| b.store(true, SeqCst)));
+            drop(deregistered);
+
+            assert!(!token.stop_requested());
+            assert!(source.request_stop());
+            assert!(!source.request_stop());
+            assert!(token.stop_requested());
+        }
+        assert!(a.load(SeqCst));
+        assert!(!b.load(SeqCst));
+
+        let _late = pown!(token.register(|| c.store(true, SeqCst)));
+        assert!(c.load(SeqCst));
+    }
+
+    #[test]
+    fn env_callbacks() {
+        let source = InPlaceStopSource::new();
+        // SAFETY: The source outlives the environment.
+        let env = unsafe { StopEnv::new(EmptyEnv, NonNull::from_ref(&source)) };
+        let stopped = AtomicBool::new(false);
+        let callback = pown!(init::value(EnvStopCallback::new()));
+        // SAFETY: The callback is pinned, and dropped before `stopped`.
+        unsafe { callback.register(env, || stopped.store(true, SeqCst)) };
+        source.request_stop();
+        assert!(stopped.load(SeqCst));
+        drop(callback);
+    }
+}
@@ -1,19 +1,48 @@
-use core::{mem, pin::Pin};
+use core::{convert::Infallible, mem, pin::Pin};
 
 use placid::{init::InitPin, pin::POwn};
 
-pub trait Scheduler {
-    type Task: Sender<Output = ()>;
+use crate::{
+    completion::Flag,
+    domain::{DefaultDomain, Domain},
+    env::{GetCompletionScheduler, GetEnv, Queryable},
+};
+
+pub trait Scheduler: Sized {
+    type Task: Sender<Output = (), Env: for<'a> Queryable<'a, GetCompletionScheduler, Output = Self>>;
+    /// The domain whose algorithms are used for the senders that complete on
+    /// this scheduler.
+    type Domain: Domain + Default = DefaultDomain;
 
     fn schedule(&self) -> Self::Task;
+
+    /// Returns the domain of the scheduler.
+    fn domain(&self) -> Self::Domain {
+        Self::Domain::default()
+    }
 }
 
-pub trait Receiver<T> {
+/// The completion handler of an operation.
+///
+/// Exactly one of the completion methods is called for a running operation.
+/// Dropping a receiver without calling any of them means that the operation is
+/// torn down without completing, e.g. dropped before it starts, and no further
+/// work should be started for it. A stopped operation must call
+/// [`set_stopped`](Receiver::set_stopped) instead.
+pub trait Receiver<T, E = Infallible>: GetEnv {
+    /// Completes the operation with a value.
     fn set(self, value: T);
+
+    /// Completes the operation with an error.
+    fn set_error(self, error: E);
+
+    /// Completes the operation without a value or an error, e.g. when the
+    /// operation is cancelled.
+    fn set_stopped(self);
 }
 
-pub trait ReceiverFrom<S: Sender + ?Sized>: Receiver<S::Output> {}
-impl<T, S: Sender> ReceiverFrom<S> for T where T: Receiver<S::Output> {}
+pub trait ReceiverFrom<S: Sender + ?Sized>: Receiver<S::Output, S::Error> {}
+impl<T, S: Sender> ReceiverFrom<S> for T where T: Receiver<S::Output, S::Error> {}
 
 /// # Safety
 ///
@@ -45,11 +74,21 @@ pub unsafe trait OperationState {
     /// ```ignore
     /// struct WaitReceiver(Thread);
     ///
-    /// impl<T> Receiver<T> for WaitReceiver {
+    /// impl<T, E> Receiver<T, E> for WaitReceiver {
     ///     fn set(self, _: T) {
     ///         received = true;
     ///         self.0.unpark();
     ///     }
+    ///
+    ///     fn set_error(self, _: E) {
+    ///         received = true;
+    ///         self.0.unpark();
+    ///     }
+    ///
+    ///     fn set_stopped(self) {
+    ///         received = true;
+    ///         self.0.unpark();
+    ///     }
     /// }
     ///
     /// fn wait<S: SenderTo<WaitReceiver>>(sender: S) {
@@ -80,12 +119,18 @@ pub unsafe trait OperationState {
     }
 }
 
-pub trait Sender {
+pub trait Sender: GetEnv {
     type Output;
+    type Error;
+    /// Whether the sender may complete with a stopped signal.
+    ///
+    /// Dropping its operation without completion is not counted as such.
+    type Stopped: Flag;
 }
 pub type SenderOutput<S> = <S as Sender>::Output;
+pub type SenderError<S> = <S as Sender>::Error;
 
-pub trait SenderTo<Recv: Receiver<Self::Output>>: Sender {
+pub trait SenderTo<Recv: Receiver<Self::Output, Self::Error>>: Sender {
     type Operation: OperationState;
     type ConnectError: core::fmt::Debug;
 
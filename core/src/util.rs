@@ -1,34 +1,161 @@
 mod and_then;
+mod bulk;
+#[cfg(feature = "std")]
+mod catch_unwind;
+mod detached;
+mod ensure_started;
 mod future;
+mod inline;
+mod let_error;
+mod let_value;
 mod map;
+mod materialize;
+mod result;
+mod scope;
+mod scoped;
+mod split;
+mod try_connect;
+mod upon;
 mod value;
 mod wait;
+mod waiter;
+mod when_all;
+mod when_all_iter;
+mod when_all_unordered;
+mod when_any;
 
-#[cfg(feature = "std")]
-pub use self::wait::sync_wait;
 pub use self::{
-    and_then::{AndThen, and_then},
+    and_then::{AndThen, AndThenExpr, and_then},
+    bulk::{Bulk, BulkExpr, bulk},
+    detached::{DetachedRecv, start_detached},
+    ensure_started::{EnsureStarted, EnsureStartedOp, StartedRecv, ensure_started},
     future::{Async, async_},
-    map::{Map, map},
+    inline::{Inline, InlineScheduler},
+    let_error::{LetError, LetErrorExpr, LetStopped, LetStoppedExpr, let_error, let_stopped},
+    let_value::{LetFn, LetValue, LetValueExpr, LetValueState, let_value},
+    map::{Map, MapExpr, map},
+    materialize::{
+        Dematerialize, DematerializeExpr, Materialize, MaterializeExpr, MaterializeState,
+        dematerialize, materialize,
+    },
+    result::{
+        AndThenOk, AndThenOkExpr, MapErr, MapErrExpr, MapOk, MapOkExpr, OrElse, OrElseExpr,
+        and_then_ok, map_err, map_ok, or_else,
+    },
+    scope::{CountingScope, Join, JoinOp, ScopeRecv},
+    scoped::{Scope, Scoped, ScopedOp, ScopedRecv, scope},
+    split::{Split, SplitOp, SplitRecv, split},
+    try_connect::{TryConnect, TryConnectOp, TryConnectRecv, try_connect, try_start_in_place},
+    upon::{
+        UponError, UponErrorExpr, UponState, UponStopped, UponStoppedExpr, upon_error, upon_stopped,
+    },
     value::{Value, value},
-    wait::{CanceledError, wait},
+    wait::{CanceledError, WaitError, WaitRecv, wait},
+    when_all::{
+        AllErrors, AllOks, ErrorsOf, FailFastState, OksOf, TryState, TryWhenAll, TryWhenAllExpr,
+        WhenAll, WhenAllErrors, WhenAllErrorsExpr, WhenAllExpr, WhenAllFailFast,
+        WhenAllFailFastExpr, try_when_all, when_all, when_all_errors, when_all_fail_fast,
+    },
+    when_all_iter::{IterRecv, WhenAllIter, WhenAllIterOp, when_all_iter},
+    when_all_unordered::{
+        ItemReceiver, UnorderedState, WhenAllUnordered, WhenAllUnorderedExpr, when_all_unordered,
+    },
+    when_any::{FirstOf, WhenAny, WhenAnyExpr, WhenAnyState, when_any},
+};
+#[cfg(feature = "std")]
+pub use self::{
+    catch_unwind::{CatchError, CatchUnwind, CatchUnwindOp, CatchUnwindRecv, catch_unwind},
+    wait::{sync_wait, sync_wait_unwind},
 };
 
 const ONESHOT_COMPLETED: &str = "oneshot sender already completed";
 
 #[cfg(test)]
 mod tests {
-    use placid::pown;
+    use core::{marker::PhantomData, pin::Pin};
+
+    use placid::{pown, prelude::*};
 
     use super::*;
-    use crate::{OperationState, Receiver, SenderTo};
+    use crate::{
+        OperationState, Receiver, Scheduler, SenderTo,
+        basic::*,
+        completion::No,
+        env::{EmptyEnv, GetCompletionScheduler, GetEnv, Queryable},
+    };
+
+    struct FailExpr<E>(PhantomData<E>);
+
+    impl<E> SenderExpr for FailExpr<E> {
+        type Output = ();
+        type Error = E;
+        type Stopped = No;
+        type Attrs = EmptyEnv;
+        type Data = E;
+        type SubSenders = ();
+
+        fn attrs(_: &E, _: &()) -> EmptyEnv {
+            EmptyEnv
+        }
+    }
+
+    struct FailState<E, R>(Option<(E, R)>);
+
+    impl<E, R> Unpin for FailState<E, R> {}
+
+    impl<E, R: Receiver<(), E>> SenderExprTo<R> for FailExpr<E> {
+        type State = FailState<E, R>;
+        type ConnectError = core::convert::Infallible;
+        type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+        fn create_state(error: E, _: &mut (), recv: R) -> Self::CreateState {
+            init::value(FailState(Some((error, recv))))
+        }
+
+        fn start(state: Pin<&mut State<Self, R>>, _: Pin<&mut ConnectAllOps<Self, R>>)
+        where
+            State<Self, R>: ConnectAll<Self, R>,
+        {
+            let (error, recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+            recv.set_error(error);
+        }
+
+        fn complete(_: Pin<&mut State<Self, R>>, value: tsum::Sum<()>) {
+            value.unreachable();
+        }
+
+        fn error(_: Pin<&mut State<Self, R>>, error: tsum::Sum<()>) {
+            error.unreachable();
+        }
+    }
+
+    /// Completes with the error right away.
+    fn fail<E>(error: E) -> BasicSender<FailExpr<E>> {
+        BasicSender::new(error, ())
+    }
 
     struct DummyReceiver;
 
-    impl<T: core::fmt::Debug> Receiver<T> for DummyReceiver {
+    impl GetEnv for DummyReceiver {
+        type Env = EmptyEnv;
+
+        fn get_env(&self) -> EmptyEnv {
+            EmptyEnv
+        }
+    }
+
+    impl<T: core::fmt::Debug, E: core::fmt::Debug> Receiver<T, E> for DummyReceiver {
         fn set(self, value: T) {
             std::println!("received: {:?}", value);
         }
+
+        fn set_error(self, error: E) {
+            std::println!("error: {:?}", error);
+        }
+
+        fn set_stopped(self) {
+            std::println!("stopped");
+        }
     }
 
     #[test]
@@ -37,4 +164,396 @@ mod tests {
         let op = pown!(s.connect(DummyReceiver));
         OperationState::start(op);
     }
+
+    #[test]
+    fn completion_scheduler() {
+        let s = map(InlineScheduler.schedule(), |()| 1);
+        let sched = s.get_env().query(GetCompletionScheduler);
+        assert_eq!(sched, InlineScheduler);
+
+        // The continuation may complete anywhere.
+        let s = and_then(s, value);
+        let EmptyEnv = s.get_env();
+
+        let op = pown!(s.connect(DummyReceiver));
+        OperationState::start(op);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn when_all_tuple() {
+        let s = when_all((value(1), map(value('c'), |c| c as u32), value("x")));
+        assert!(matches!(sync_wait(s), Ok((1, 99, "x"))));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn when_all_iter_in_order() {
+        let s = when_all_iter((0..100).map(|i| map(value(i), |i| i * 2)));
+        let values = sync_wait(s).unwrap();
+        assert!(values.into_iter().eq((0..100).map(|i| i * 2)));
+
+        let empty = when_all_iter((0..0).map(value));
+        assert!(sync_wait(empty).unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn when_all_error_policies() {
+        let s = when_all_fail_fast((value(1), map(value(2), |i| i * 2)));
+        assert!(matches!(sync_wait(s), Ok((1, 4))));
+
+        let s = when_all_fail_fast((value(1), fail('e')));
+        assert!(matches!(sync_wait(s), Err(WaitError::Error(_))));
+
+        let pending = when_all_fail_fast((async_(core::future::pending::<i32>()),));
+        let s = when_all_fail_fast((pending, fail('e')));
+        assert!(matches!(sync_wait(s), Err(WaitError::Error(_))));
+
+        let s = when_all_errors((fail(1u8), value(2), fail(3u8)));
+        assert!(matches!(
+            sync_wait(s),
+            Err(WaitError::Error((Some(1), None, Some(3))))
+        ));
+
+        let s = when_all_errors((value(1), value('c')));
+        assert!(matches!(sync_wait(s), Ok((1, 'c'))));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn when_all_unordered_items() {
+        let items = std::sync::Mutex::new(alloc::vec::Vec::new());
+        let senders = (value(1), map(value(2), |i| i * 10), value(3));
+        let s = when_all_unordered(senders, |i: usize, v: i32| {
+            items.lock().unwrap().push((i, v))
+        });
+        assert!(sync_wait(s).is_ok());
+
+        let mut items = items.into_inner().unwrap();
+        items.sort();
+        assert_eq!(items, [(0, 1), (1, 20), (2, 3)]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn ensure_started_eagerly() {
+        use alloc::sync::Arc;
+        use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        let s = ensure_started(map(value(1), move |i| {
+            counter.fetch_add(1, Relaxed);
+            i + 1
+        }))
+        .unwrap();
+        assert_eq!(runs.load(Relaxed), 1);
+        // The consumer may borrow.
+        let offset = 1;
+        assert!(matches!(sync_wait(map(s, |i| i + offset)), Ok(3)));
+
+        drop(ensure_started(value(())).unwrap());
+
+        // Dropping the sender stops the pending operation, which drops the future.
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let s = ensure_started(async_(async move {
+            let _tx = tx;
+            core::future::pending::<()>().await
+        }))
+        .unwrap();
+        drop(s);
+        let timeout = std::time::Duration::from_secs(5);
+        assert!(matches!(
+            rx.recv_timeout(timeout),
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected)
+        ));
+
+        // So does dropping a waiting consumer, which is unlinked at the end of the
+        // scope.
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let s = ensure_started(async_(async move {
+            let _tx = tx;
+            core::future::pending::<()>().await
+        }))
+        .unwrap();
+        {
+            let op = pown!(s.connect(DummyReceiver));
+            OperationState::start(op);
+        }
+        assert!(matches!(
+            rx.recv_timeout(timeout),
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn detached() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        start_detached(map(value(3), move |i| tx.send(i).unwrap())).unwrap();
+        assert_eq!(rx.recv().unwrap(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn split_runs_once() {
+        use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+        let runs = AtomicUsize::new(0);
+        let s = split(map(value(1), |i| {
+            runs.fetch_add(1, Relaxed);
+            i + 1
+        }))
+        .unwrap();
+
+        // The consumers may borrow.
+        let offset = 1;
+        assert!(matches!(sync_wait(map(s.clone(), |i| i + offset)), Ok(3)));
+        assert!(matches!(sync_wait(s), Ok(2)));
+        assert_eq!(runs.load(Relaxed), 1);
+
+        // A waiting consumer is unlinked when it is dropped at the end of the
+        // scope.
+        let s = split(async_(core::future::pending::<i32>())).unwrap();
+        {
+            let op = pown!(s.clone().connect(DummyReceiver));
+            OperationState::start(op);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn when_any_first_wins() {
+        let s = when_any((map(value(1), |i| i + 1), value("x")));
+        let first = sync_wait(s).unwrap();
+        assert!(matches!(crate::list::split_first(first), Ok(2)));
+
+        let empty = when_any(());
+        assert!(matches!(sync_wait(empty), Err(WaitError::Canceled(_))));
+
+        // The pending loser observes the stop request.
+        let s = when_any((value(1), async_(core::future::pending::<i32>())));
+        let first = sync_wait(s).unwrap();
+        assert!(matches!(crate::list::split_first(first), Ok(1)));
+
+        // So does the nested one, to which the request is forwarded.
+        let pending = when_any((async_(core::future::pending::<i32>()),));
+        let s = when_any((pending, value(2)));
+        let first = sync_wait(s).unwrap();
+        assert!(matches!(crate::list::split_first(first), Err(_)));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn counting_scope_joins() {
+        let scope = CountingScope::new();
+        assert!(matches!(sync_wait(scope.join()), Ok(())));
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        for i in 0..3 {
+            let tx = tx.clone();
+            scope
+                .spawn(map(value(i), move |i| tx.send(i).unwrap()))
+                .unwrap();
+        }
+        assert!(matches!(sync_wait(scope.join()), Ok(())));
+
+        let mut items = rx.try_iter().collect::<alloc::vec::Vec<_>>();
+        items.sort();
+        assert_eq!(items, [0, 1, 2]);
+
+        // The pending operation never joins unless it observes the stop request.
+        scope.spawn(async_(core::future::pending::<()>())).unwrap();
+        assert!(scope.request_stop());
+        assert!(matches!(sync_wait(scope.join()), Ok(())));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn scope_borrows() {
+        use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+        let items = [1, 2, 3];
+        let sum = AtomicUsize::new(0);
+        let s = scope(|s| {
+            for &i in &items {
+                s.spawn(map(value(i), |i| {
+                    sum.fetch_add(i, Relaxed);
+                }))
+                .unwrap();
+            }
+            items.len()
+        });
+        assert!(matches!(sync_wait(s), Ok(3)));
+        assert_eq!(sum.load(Relaxed), 6);
+
+        let s = scope(|s| {
+            s.spawn(async_(core::future::pending::<()>())).unwrap();
+            s.request_stop()
+        });
+        assert!(matches!(sync_wait(s), Ok(true)));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn let_value_borrows() {
+        use alloc::string::String;
+
+        fn first_word(text: &mut String) -> Map<Value<&str>, fn(&str) -> usize> {
+            let word = text.split(' ').next().unwrap_or_default();
+            map(value(word), str::len)
+        }
+
+        let s = let_value(value(String::from("hello world")), first_word);
+        assert!(matches!(sync_wait(s), Ok(5)));
+
+        // The value itself may borrow.
+        let text = String::from("hi");
+        let s = let_value(value(text.as_str()), |text: &mut &str| value(text.len()));
+        assert!(matches!(sync_wait(s), Ok(2)));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn error_and_stopped_recovery() {
+        let s = upon_error(fail(3), |e| assert_eq!(e, 3));
+        assert!(matches!(sync_wait(s), Ok(())));
+
+        let s = let_error(fail(1), |_| value(()));
+        assert!(matches!(sync_wait(s), Ok(())));
+        let s = let_error(fail(1), |e| fail(e + 1));
+        assert!(matches!(sync_wait(s), Err(WaitError::Error(2))));
+
+        let s = upon_stopped(map(when_any(()), |_| 0), || 7);
+        assert!(matches!(sync_wait(s), Ok(7)));
+        let s = let_stopped(map(when_any(()), |_| 0), || map(when_any(()), |_| 8));
+        assert!(matches!(sync_wait(s), Err(WaitError::Canceled(_))));
+
+        // Tearing down the operation is not a stopped completion.
+        let s = let_stopped(map(value(1), |i| i), || -> Value<i32> { unreachable!() });
+        let op = pown!(s.connect(DummyReceiver));
+        drop(op);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn result_combinators() {
+        let s = map_ok(value(Ok::<_, ()>(1)), |i| i + 1);
+        assert!(matches!(sync_wait(s), Ok(Ok(2))));
+        let s = map_err(value(Err::<(), _>(1)), |e| e + 1);
+        assert!(matches!(sync_wait(s), Ok(Err(2))));
+
+        let s = and_then_ok(value(Ok::<_, i32>(1)), |i| value(Ok(i + 1)));
+        assert!(matches!(sync_wait(s), Ok(Ok(2))));
+        let s = and_then_ok(value(Err::<i32, _>(1)), |_| -> Value<Result<i32, i32>> {
+            unreachable!("the continuation is short-circuited")
+        });
+        assert!(matches!(sync_wait(s), Ok(Err(1))));
+        let s = or_else(value(Err::<i32, _>(1)), |e| value(Ok::<_, ()>(e + 1)));
+        assert!(matches!(sync_wait(s), Ok(Ok(2))));
+
+        let s = try_when_all((value(Ok::<_, i32>(1)), value(Ok("x"))));
+        assert!(matches!(sync_wait(s), Ok(Ok((1, "x")))));
+        let s = try_when_all((value(Ok::<i32, i32>(1)), value(Err::<&str, _>(2))));
+        assert!(matches!(sync_wait(s), Ok(Err(2))));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn try_connect_reports() {
+        use placid::init::InitPinError;
+
+        use crate::{ConnectOp, Sender};
+
+        /// Fails to connect with the error.
+        struct Refuse(i32);
+
+        impl GetEnv for Refuse {
+            type Env = EmptyEnv;
+
+            fn get_env(&self) -> EmptyEnv {
+                EmptyEnv
+            }
+        }
+
+        impl Sender for Refuse {
+            type Output = ();
+            type Error = i32;
+            type Stopped = No;
+        }
+
+        impl<R: Receiver<(), i32>> SenderTo<R> for Refuse {
+            type Operation = ConnectOp<BasicSender<FailExpr<i32>>, R>;
+            type ConnectError = i32;
+
+            fn connect(self, _: R) -> impl InitPin<Self::Operation, Error = i32> {
+                init::try_raw_pin(move |uninit: Uninit<Self::Operation>, slot| {
+                    Err(InitPinError::new(self.0, uninit, slot))
+                })
+            }
+        }
+
+        assert!(matches!(sync_wait(try_connect(value(1))), Ok(1)));
+        assert!(matches!(
+            sync_wait(try_connect(fail(2))),
+            Err(WaitError::Error(2))
+        ));
+        assert!(matches!(
+            sync_wait(try_connect(Refuse(3))),
+            Err(WaitError::Error(3))
+        ));
+        assert!(matches!(
+            sync_wait(let_error(fail(4), |e| Refuse(e + 1))),
+            Err(WaitError::Error(5))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn catch_unwind_resumes() {
+        let s = catch_unwind(map(value(1), |x: i32| -> i32 { panic!("boom {x}") }));
+        assert!(matches!(
+            sync_wait(s),
+            Err(WaitError::Error(CatchError::Panic(_)))
+        ));
+
+        let s = catch_unwind(fail(2));
+        assert!(matches!(sync_wait_unwind(s), Err(WaitError::Error(2))));
+
+        let s = catch_unwind(map(value(3), |x: i32| -> i32 { panic!("boom {x}") }));
+        let wait = std::panic::AssertUnwindSafe(|| sync_wait_unwind(s));
+        let payload = std::panic::catch_unwind(wait).unwrap_err();
+        assert_eq!(
+            payload.downcast_ref::<alloc::string::String>().unwrap(),
+            "boom 3"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn materialize_round_trip() {
+        use crate::completion::Completion;
+
+        assert!(matches!(
+            sync_wait(materialize(value(1))),
+            Ok(Completion::Value(1))
+        ));
+        assert!(matches!(
+            sync_wait(materialize(fail(2))),
+            Ok(Completion::Error(2))
+        ));
+        let s = materialize(map(when_any(()), |_| 0));
+        assert!(matches!(sync_wait(s), Ok(Completion::Stopped)));
+
+        let s = dematerialize(materialize(fail(3)));
+        assert!(matches!(sync_wait(s), Err(WaitError::Error(3))));
+        let s = dematerialize(value(Completion::<i32, i32>::Stopped));
+        assert!(matches!(sync_wait(s), Err(WaitError::Canceled(_))));
+
+        // Tearing down the operation does not complete it with `Stopped`.
+        let s = map(materialize(value(1)), |_| -> i32 { unreachable!() });
+        let op = pown!(s.connect(DummyReceiver));
+        drop(op);
+    }
 }
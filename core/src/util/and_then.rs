@@ -2,13 +2,23 @@ use core::{
     convert::Infallible,
     marker::{PhantomData, PhantomPinned},
     pin::Pin,
+    ptr::NonNull,
 };
 
 use pin_project::pin_project;
 use placid::{place::DynPlace, prelude::*};
 use tsum::{Sum, T, t};
 
-use crate::{OperationState, ReceiverFrom, Sender, SenderTo, basic::*, util::ONESHOT_COMPLETED};
+use crate::{
+    Receiver, Sender, SenderTo,
+    basic::*,
+    completion::FlagOr,
+    env::EmptyEnv,
+    util::{
+        ONESHOT_COMPLETED,
+        try_connect::{TryConnectRecv, try_start_in_place},
+    },
+};
 
 pub struct AndThenExpr<S, F>(PhantomData<(S, F)>);
 
@@ -17,62 +27,118 @@ pub struct AndThenExpr<S, F>(PhantomData<(S, F)>);
 pub struct AndThenState<O, F, R> {
     #[pin]
     pinned: PhantomPinned,
-    data: Option<(F, R)>,
+    func: Option<F>,
+    // `next_op` must come before `recv`, since its receiver refers to it.
     #[pin]
     next_op: DynPlace<O>,
+    recv: Option<R>,
+}
+
+impl<O, F, R> AndThenState<O, F, R> {
+    pub(super) fn new(func: F, recv: R) -> impl InitPin<Self, Error = Infallible> {
+        init_pin!(AndThenState {
+            pinned: PhantomPinned,
+            func: || Some(func),
+            next_op: DynPlace::new,
+            recv: || Some(recv),
+        })
+    }
+
+    pub(super) fn take(self: Pin<&mut Self>) -> Option<(F, R)> {
+        let this = self.project();
+        let func = this.func.take()?;
+        Some((func, this.recv.take().expect(ONESHOT_COMPLETED)))
+    }
+
+    /// Connects the continuation to the receiver, and starts it in place.
+    ///
+    /// If the connection fails, the receiver completes with the connect error
+    /// as an error instead, like in [`try_connect`](super::try_connect).
+    pub(super) fn start_next<T>(self: Pin<&mut Self>, next_snd: T, recv: R)
+    where
+        T: SenderTo<TryConnectRecv<R>, Operation = O, ConnectError: Into<T::Error>>,
+        R: Receiver<T::Output, T::Error>,
+    {
+        let this = self.project();
+        *this.recv = Some(recv);
+        // SAFETY: `recv` lives after the operation in the state, which is started
+        // only once here and not forgotten after started since it requires outer
+        // `OperationState::start`.
+        unsafe { try_start_in_place(next_snd, this.next_op, NonNull::from_mut(this.recv)) }
+    }
 }
 
 impl<S, F, T> SenderExpr for AndThenExpr<S, F>
 where
     S: Sender,
     F: FnOnce(S::Output) -> T,
-    T: Sender,
+    T: Sender<Error = S::Error>,
 {
     type Output = T::Output;
+    type Error = T::Error;
+    type Stopped = FlagOr<S::Stopped, T::Stopped>;
+    // The continuation may complete elsewhere, e.g. on another scheduler, so
+    // nothing is known about the completion of the whole.
+    type Attrs = EmptyEnv;
     type Data = F;
     type SubSenders = T![S];
+
+    fn attrs(_: &F, _: &T![S]) -> EmptyEnv {
+        EmptyEnv
+    }
 }
 
 impl<S, F, T, R> SenderExprTo<R> for AndThenExpr<S, F>
 where
     S: Sender,
     F: FnOnce(S::Output) -> T,
-    T: SenderTo<R, Operation: Sized>,
-    R: ReceiverFrom<T>,
+    T: SenderTo<
+            TryConnectRecv<R>,
+            Operation: Sized,
+            Error = S::Error,
+            ConnectError: Into<S::Error>,
+        >,
+    R: Receiver<T::Output, S::Error>,
 {
     type State = AndThenState<T::Operation, F, R>;
-    type Error = Infallible;
-    type CreateState = impl InitPin<Self::State, Error = Self::Error>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
 
     fn create_state(data: Self::Data, _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
-        init_pin!(AndThenState {
-            pinned: PhantomPinned,
-            data: || Some((data, recv)),
-            next_op: DynPlace::new,
-        })
+        AndThenState::new(data, recv)
     }
 
     fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
-        let state = state.state_mut().project();
-        let (func, recv) = state.data.take().expect(ONESHOT_COMPLETED);
-        let next_snd = func(value.into_inner());
-        let next_op = next_snd.connect(recv);
-
-        if let Ok(next_op) = state.next_op.try_insert_pin(next_op) {
-            // SAFETY: The operation is started only once here, and the state is not
-            // forgotten after started since it requires outer `OperationState::start`.
-            unsafe { next_op.start_by_ref() };
+        let mut state = state.state_mut();
+        let (func, recv) = state.as_mut().take().expect(ONESHOT_COMPLETED);
+        state.start_next(func(value.into_inner()), recv);
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let (_, recv) = state.state_mut().take().expect(ONESHOT_COMPLETED);
+        recv.set_error(error.into_inner());
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        if let Some((_, recv)) = state.state_mut().take() {
+            recv.set_stopped();
         }
     }
 }
 
 pub type AndThen<S, F> = BasicSender<AndThenExpr<S, F>>;
 
+/// Continues with the sender created from the output of the sender.
+///
+/// The continuation is connected once the sender completes. If the connection
+/// fails, the receiver completes with the connect error converted into the
+/// error type. The same holds for the other continuations, e.g.
+/// [`let_value`](super::let_value) and [`let_error`](super::let_error).
 pub const fn and_then<S, F, T>(sender: S, func: F) -> AndThen<S, F>
 where
     S: Sender,
     F: FnOnce(S::Output) -> T,
-    T: Sender,
+    T: Sender<Error = S::Error>,
 {
     BasicSender::new(func, t![sender])
 }
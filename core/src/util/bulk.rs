@@ -0,0 +1,88 @@
+use core::{convert::Infallible, marker::PhantomData, pin::Pin};
+
+use placid::prelude::*;
+use tsum::{Sum, T, t};
+
+use crate::{
+    Receiver, Sender,
+    basic::*,
+    completion::Yes,
+    env::{EnvOf, GetEnv},
+    util::ONESHOT_COMPLETED,
+};
+
+pub struct BulkExpr<S, F>(PhantomData<(S, F)>);
+
+impl<S, F> SenderExpr for BulkExpr<S, F>
+where
+    S: Sender,
+    F: FnMut(usize, &mut S::Output),
+{
+    type Output = S::Output;
+    type Error = S::Error;
+    type Stopped = S::Stopped;
+    type Attrs = EnvOf<S>;
+    type Data = (usize, F);
+    type SubSenders = T![S];
+    type Customizable = Yes;
+
+    fn attrs(_: &(usize, F), sub_senders: &T![S]) -> EnvOf<S> {
+        sub_senders.0.get_env()
+    }
+}
+
+pub struct BulkState<T>(Option<T>);
+
+impl<T> Unpin for BulkState<T> {}
+
+impl<S, F, R> SenderExprTo<R> for BulkExpr<S, F>
+where
+    S: Sender,
+    F: FnMut(usize, &mut S::Output),
+    R: Receiver<S::Output, S::Error>,
+{
+    type State = BulkState<(usize, F, R)>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(
+        (shape, func): Self::Data,
+        _: &mut Self::SubSenders,
+        recv: R,
+    ) -> Self::CreateState {
+        init::with(move || BulkState(Some((shape, func, recv))))
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let (shape, mut func, recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        let mut value = value.into_inner();
+        (0..shape).for_each(|index| func(index, &mut value));
+        recv.set(value);
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let (.., recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set_error(error.into_inner());
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        if let Some((.., recv)) = state.state_mut().0.take() {
+            recv.set_stopped();
+        }
+    }
+}
+
+pub type Bulk<S, F> = BasicSender<BulkExpr<S, F>>;
+
+/// Invokes `func` with every index in `0..shape` and the output of `sender`,
+/// then forwards the output.
+///
+/// The default implementation runs sequentially. Domains may customize it
+/// through [`Customizes`](crate::domain::Customizes).
+pub const fn bulk<S, F>(sender: S, shape: usize, func: F) -> Bulk<S, F>
+where
+    S: Sender,
+    F: FnMut(usize, &mut S::Output),
+{
+    BasicSender::new((shape, func), t![sender])
+}
@@ -0,0 +1,207 @@
+use alloc::boxed::Box;
+use core::{
+    any::Any,
+    convert::Infallible,
+    marker::PhantomPinned,
+    mem::{self, ManuallyDrop, MaybeUninit},
+    pin::Pin,
+    ptr::NonNull,
+};
+use std::panic::{self, AssertUnwindSafe};
+
+use pin_project::pin_project;
+use placid::{
+    pin::{DropSlot, DroppingSlot},
+    prelude::*,
+};
+
+use crate::{
+    OperationState, Receiver, Sender, SenderTo,
+    basic::OnceSlot,
+    env::{EnvOf, GetEnv},
+    util::ONESHOT_COMPLETED,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CatchError<E> {
+    #[error("the sender operation completed with an error")]
+    Error(E),
+    #[error("the sender operation panicked")]
+    Panic(Box<dyn Any + Send + 'static>),
+}
+
+/// The receiver of the inner operation of [`CatchUnwind`].
+pub struct CatchUnwindRecv<R: GetEnv> {
+    // Effectively a `&'op OnceSlot<R>`.
+    recv: NonNull<OnceSlot<R>>,
+    env: EnvOf<R>,
+}
+
+// SAFETY: The slot is synchronized.
+unsafe impl<R: GetEnv<Env: Send> + Send> Send for CatchUnwindRecv<R> {}
+// SAFETY: Same as above.
+unsafe impl<R: GetEnv<Env: Sync> + Send> Sync for CatchUnwindRecv<R> {}
+
+impl<R: GetEnv> CatchUnwindRecv<R> {
+    fn take(self) -> R {
+        // SAFETY: `recv` lives after the inner operation, and thus this receiver,
+        // in the operation state.
+        let recv = unsafe { self.recv.as_ref() };
+        recv.take().expect(ONESHOT_COMPLETED)
+    }
+}
+
+impl<R: GetEnv> GetEnv for CatchUnwindRecv<R> {
+    type Env = EnvOf<R>;
+
+    fn get_env(&self) -> EnvOf<R> {
+        self.env.clone()
+    }
+}
+
+impl<T, E, R: Receiver<T, CatchError<E>>> Receiver<T, E> for CatchUnwindRecv<R> {
+    fn set(self, value: T) {
+        self.take().set(value);
+    }
+
+    fn set_error(self, error: E) {
+        self.take().set_error(CatchError::Error(error));
+    }
+
+    fn set_stopped(self) {
+        self.take().set_stopped();
+    }
+}
+
+/// A sender that completes with the payload of the panic raised while the
+/// inner sender connects or starts.
+pub struct CatchUnwind<S>(S);
+
+impl<S: Sender> GetEnv for CatchUnwind<S> {
+    type Env = EnvOf<S>;
+
+    fn get_env(&self) -> EnvOf<S> {
+        self.0.get_env()
+    }
+}
+
+impl<S: Sender> Sender for CatchUnwind<S> {
+    type Output = S::Output;
+    type Error = CatchError<S::Error>;
+    type Stopped = S::Stopped;
+}
+
+/// The inner operation, which is written in place on starting and may be torn
+/// down early.
+struct NextOp<O> {
+    op: MaybeUninit<O>,
+    connected: bool,
+}
+
+impl<O> NextOp<O> {
+    fn clear(&mut self) {
+        if mem::take(&mut self.connected) {
+            // SAFETY: The operation is initialized, and `connected` is cleared so that
+            // it is not dropped again.
+            unsafe { self.op.assume_init_drop() };
+        }
+    }
+}
+
+impl<O> Drop for NextOp<O> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[derive(InitPin)]
+#[pin_project]
+pub struct CatchUnwindOp<S, O, R> {
+    #[pin]
+    pinned: PhantomPinned,
+    sender: Option<S>,
+    // `next_op` must come before `recv`, since its receiver refers to it.
+    next_op: NextOp<O>,
+    recv: OnceSlot<R>,
+}
+
+unsafe impl<S, R> OperationState for CatchUnwindOp<S, S::Operation, R>
+where
+    S: SenderTo<CatchUnwindRecv<R>, Operation: Sized, ConnectError = Infallible>,
+    R: Receiver<S::Output, CatchError<S::Error>>,
+{
+    unsafe fn start_by_ref(self: Pin<&mut Self>) {
+        let this = self.project();
+        let sender = this.sender.take().expect(ONESHOT_COMPLETED);
+        let env = this.recv.get_mut().expect(ONESHOT_COMPLETED).get_env();
+        let recv = NonNull::from_ref(&*this.recv);
+        let next_op = this.next_op;
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut subslot = ManuallyDrop::new(DroppingSlot::new());
+            // SAFETY: The operation is written in place and dropped along with the
+            // state once `connected` is set. It is started only once here, and the
+            // state is not forgotten after started since it requires outer
+            // `OperationState::start`.
+            unsafe {
+                let subslot_ref = DropSlot::new_unchecked(&mut subslot);
+                let next = sender.connect(CatchUnwindRecv { recv, env });
+                match Uninit::from_raw(next_op.op.as_mut_ptr()).try_write_pin(next, subslot_ref) {
+                    Ok(mut p) => {
+                        next_op.connected = true;
+                        p.as_mut().start_by_ref();
+                        mem::forget(p);
+                    }
+                    Err(err) => match err.error {},
+                }
+            }
+        }));
+
+        if let Err(payload) = result {
+            // The inner operation may still be running elsewhere, e.g. a sibling
+            // scheduled on another thread, so it is torn down before completing.
+            next_op.clear();
+            // SAFETY: The slot lives in the operation state, which is not completed
+            // yet if the receiver is still there.
+            match unsafe { recv.as_ref() }.take() {
+                Some(recv) => recv.set_error(CatchError::Panic(payload)),
+                // The panic is raised after completion, e.g. by the receiver.
+                None => panic::resume_unwind(payload),
+            }
+        }
+    }
+}
+
+impl<S, R> SenderTo<R> for CatchUnwind<S>
+where
+    S: SenderTo<CatchUnwindRecv<R>, Operation: Sized, ConnectError = Infallible>,
+    R: Receiver<S::Output, CatchError<S::Error>>,
+{
+    type Operation = CatchUnwindOp<S, S::Operation, R>;
+    type ConnectError = Infallible;
+
+    fn connect(self, receiver: R) -> impl InitPin<Self::Operation, Error = Self::ConnectError> {
+        init_pin!(CatchUnwindOp {
+            pinned: PhantomPinned,
+            sender: || Some(self.0),
+            next_op: || NextOp {
+                op: MaybeUninit::uninit(),
+                connected: false,
+            },
+            recv: || OnceSlot::full(receiver),
+        })
+    }
+}
+
+/// Catches the panic raised while the sender connects or starts, including
+/// any completion delivered inline, and completes with its payload as an
+/// error instead. The inner operation is torn down before that, waiting for
+/// the parts of it that are still running elsewhere.
+///
+/// Only the panics raised inline are caught. Work that completes on another
+/// thread, e.g. a `map` after a scheduler task, runs on that thread outside of
+/// this adaptor; to catch panics there, wrap the work that is started on it
+/// instead, e.g. the sender passed to `sched_on`.
+pub const fn catch_unwind<S: Sender>(sender: S) -> CatchUnwind<S> {
+    CatchUnwind(sender)
+}
@@ -0,0 +1,136 @@
+use alloc::boxed::Box;
+use core::{
+    convert::Infallible,
+    mem::{self, ManuallyDrop, MaybeUninit},
+    pin::Pin,
+    ptr::NonNull,
+};
+
+use placid::{
+    pin::{DropSlot, DroppingSlot},
+    prelude::*,
+};
+
+use crate::{
+    OperationState, Receiver, SenderTo,
+    env::{EmptyEnv, GetEnv},
+};
+
+/// The heap allocation of a detached operation.
+struct Detached<O> {
+    // Whether `op` has been connected, so that a receiver dropped by a failed
+    // connection does not free the allocation.
+    connected: bool,
+    op: O,
+}
+
+/// # Safety
+///
+/// `ptr` must come from `Box::<MaybeUninit<Detached<O>>>::into_raw`, and this
+/// function must be called at most once for it.
+unsafe fn release<O>(ptr: NonNull<()>) {
+    let ptr = ptr.cast::<Detached<O>>().as_ptr();
+    // SAFETY: `op` is initialized once connected, and never moved since.
+    unsafe {
+        if (*ptr).connected {
+            drop(Box::from_raw(ptr));
+        }
+    }
+}
+
+/// The owner of a detached operation, held by its receiver, which frees the
+/// operation state on drop without naming its type.
+pub(super) struct HeapOp {
+    ptr: NonNull<()>,
+    release: unsafe fn(NonNull<()>),
+}
+
+// SAFETY: Detached operations are required to be `Send`.
+unsafe impl Send for HeapOp {}
+// SAFETY: The operation cannot be accessed through a shared reference.
+unsafe impl Sync for HeapOp {}
+
+impl Drop for HeapOp {
+    fn drop(&mut self) {
+        // SAFETY: The receiver is the only one that releases the operation, and the
+        // operation does not touch itself after completing the receiver.
+        unsafe { (self.release)(self.ptr) };
+    }
+}
+
+/// Connects the sender to the receiver made from the owner of the operation,
+/// and starts it in a heap allocation.
+///
+/// # Safety
+///
+/// Whatever the operation borrows must outlive it, i.e. until its receiver is
+/// completed or dropped.
+pub(super) unsafe fn start_in_heap<S, R, F>(sender: S, make_recv: F) -> Result<(), S::ConnectError>
+where
+    S: SenderTo<R, Operation: Send>,
+    R: Receiver<S::Output, S::Error>,
+    F: FnOnce(HeapOp) -> R,
+{
+    let ptr = Box::into_raw(Box::<Detached<S::Operation>>::new_uninit());
+    // SAFETY: The operation is connected in place, and the allocation is owned by
+    // the receiver once `connected` is set. Until then, it is freed here on
+    // failure.
+    unsafe {
+        let detached = MaybeUninit::as_mut_ptr(&mut *ptr);
+        (&raw mut (*detached).connected).write(false);
+        let recv = make_recv(HeapOp {
+            ptr: NonNull::new_unchecked(detached).cast(),
+            release: release::<S::Operation>,
+        });
+
+        let mut subslot = ManuallyDrop::new(DroppingSlot::new());
+        let subslot_ref = DropSlot::new_unchecked(&mut subslot);
+        let op = &raw mut (*detached).op;
+        match Uninit::from_raw(op).try_write_pin(sender.connect(recv), subslot_ref) {
+            Ok(p) => mem::forget(p),
+            Err(err) => {
+                drop(Box::from_raw(ptr));
+                return Err(err.error);
+            }
+        }
+        (*detached).connected = true;
+        Pin::new_unchecked(&mut *op).start_by_ref();
+    }
+    Ok(())
+}
+
+/// The receiver of a detached operation, which frees the operation state on
+/// completion.
+pub struct DetachedRecv {
+    _op: HeapOp,
+}
+
+impl GetEnv for DetachedRecv {
+    type Env = EmptyEnv;
+
+    fn get_env(&self) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<T> Receiver<T, Infallible> for DetachedRecv {
+    fn set(self, _: T) {}
+
+    fn set_error(self, error: Infallible) {
+        match error {}
+    }
+
+    fn set_stopped(self) {}
+}
+
+/// Connects the sender and starts it in a heap-allocated operation state, which
+/// is freed once the operation completes.
+///
+/// The values are discarded, and errors must have been handled beforehand.
+pub fn start_detached<S>(sender: S) -> Result<(), S::ConnectError>
+where
+    S: SenderTo<DetachedRecv, Error = Infallible, Operation: Send + 'static>,
+{
+    // SAFETY: The operation is `'static`.
+    unsafe { start_in_heap(sender, |op| DetachedRecv { _op: op }) }
+}
@@ -0,0 +1,273 @@
+use alloc::sync::Arc;
+use core::{
+    cell::UnsafeCell,
+    convert::Infallible,
+    mem::{self, ManuallyDrop},
+    pin::Pin,
+    ptr::NonNull,
+};
+
+use placid::{
+    pin::{DropSlot, DroppingSlot},
+    prelude::*,
+};
+
+use crate::{
+    OperationState, Receiver, Sender, SenderTo,
+    basic::OnceSlot,
+    completion::Completion,
+    env::{EmptyEnv, GetEnv, StopEnv},
+    stop::{Callbacks, InPlaceStopSource},
+    util::{
+        ONESHOT_COMPLETED,
+        waiter::{Waited, WaiterOp},
+    },
+};
+
+struct Core<T, E> {
+    source: InPlaceStopSource,
+    // Set with the list of waiters locked, and taken by the consumer.
+    completion: OnceSlot<Completion<T, E>>,
+    // The consumer if it starts before the completion, linked in place in its
+    // operation state.
+    waiter: Callbacks,
+}
+
+struct Started<O, T, E> {
+    // `op` must come first, since its receiver refers to `core`.
+    op: UnsafeCell<O>,
+    core: Core<T, E>,
+}
+
+// SAFETY: `op` is only accessed on starting it, before the `Arc` is shared.
+unsafe impl<O: Send, T: Send, E: Send> Send for Started<O, T, E> {}
+// SAFETY: Same as above, and the core is synchronized.
+unsafe impl<O: Send, T: Send, E: Send> Sync for Started<O, T, E> {}
+
+impl<O, T, E> Waited for Started<O, T, E> {
+    type Output = T;
+    type Error = E;
+
+    fn waiters(&self) -> &Callbacks {
+        &self.core.waiter
+    }
+
+    fn completion(&self) -> Option<Completion<T, E>> {
+        self.core.completion.take()
+    }
+
+    fn abandoned(&self) {
+        // The result is no longer wanted.
+        self.core.source.request_stop();
+    }
+}
+
+/// Releases the reference to the shared state that the running operation
+/// holds, without naming the type of the operation.
+struct Release {
+    owner: NonNull<()>,
+    drop: unsafe fn(NonNull<()>),
+}
+
+/// # Safety
+///
+/// `owner` must come from `Arc::<X>::into_raw`.
+unsafe fn release<X>(owner: NonNull<()>) {
+    // SAFETY: Guaranteed by the caller.
+    drop(unsafe { Arc::from_raw(owner.cast::<X>().as_ptr()) });
+}
+
+/// The receiver of the operation started by [`ensure_started`].
+pub struct StartedRecv<T, E> {
+    // Effectively a `&'shared Core<T, E>`, kept alive by `release`.
+    core: NonNull<Core<T, E>>,
+    release: Release,
+}
+
+// SAFETY: The core is synchronized.
+unsafe impl<T: Send, E: Send> Send for StartedRecv<T, E> {}
+// SAFETY: Same as above.
+unsafe impl<T: Send, E: Send> Sync for StartedRecv<T, E> {}
+
+impl<T, E> StartedRecv<T, E> {
+    fn core(&self) -> &Core<T, E> {
+        // SAFETY: The shared state is not released until this receiver is dropped.
+        unsafe { self.core.as_ref() }
+    }
+
+    fn finish(self, completion: Completion<T, E>) {
+        let core = self.core();
+        let waiter = core.waiter.lock();
+        if core.completion.set(completion).is_err() {
+            unreachable!("the operation completed twice");
+        }
+        drop(waiter);
+        core.waiter.execute_all();
+    }
+}
+
+impl<T, E> GetEnv for StartedRecv<T, E> {
+    type Env = StopEnv<EmptyEnv>;
+
+    fn get_env(&self) -> StopEnv<EmptyEnv> {
+        // SAFETY: The stop source lives in the shared state, which outlives the
+        // operation.
+        unsafe { StopEnv::new(EmptyEnv, NonNull::from_ref(&self.core().source)) }
+    }
+}
+
+impl<T, E> Receiver<T, E> for StartedRecv<T, E> {
+    fn set(self, value: T) {
+        self.finish(Completion::Value(value));
+    }
+
+    fn set_error(self, error: E) {
+        self.finish(Completion::Error(error));
+    }
+
+    fn set_stopped(self) {
+        self.finish(Completion::Stopped);
+    }
+}
+
+impl<T, E> Drop for StartedRecv<T, E> {
+    fn drop(&mut self) {
+        // The reference to the shared state is released once the receiver is done
+        // with it, whether it has completed or is torn down without completion,
+        // e.g. if the connection fails. In the latter case, a waiting consumer is
+        // never completed, and its receiver is dropped along with it.
+        // SAFETY: The reference is released only once here, and the core is not
+        // accessed afterwards.
+        unsafe { (self.release.drop)(self.release.owner) };
+    }
+}
+
+type Shared<S> = Started<
+    <S as SenderTo<StartedRecv<StartedOutput<S>, StartedError<S>>>>::Operation,
+    StartedOutput<S>,
+    StartedError<S>,
+>;
+type StartedOutput<S> = <S as Sender>::Output;
+type StartedError<S> = <S as Sender>::Error;
+
+/// A sender for the result of an operation that has already started.
+///
+/// Dropping it without connecting requests the operation to stop.
+pub struct EnsureStarted<S>(Option<Arc<Shared<S>>>)
+where
+    S: SenderTo<StartedRecv<S::Output, S::Error>>;
+
+impl<S> Drop for EnsureStarted<S>
+where
+    S: SenderTo<StartedRecv<S::Output, S::Error>>,
+{
+    fn drop(&mut self) {
+        if let Some(shared) = self.0.take() {
+            shared.core.source.request_stop();
+        }
+    }
+}
+
+impl<S> GetEnv for EnsureStarted<S>
+where
+    S: SenderTo<StartedRecv<S::Output, S::Error>>,
+{
+    type Env = EmptyEnv;
+
+    fn get_env(&self) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<S> Sender for EnsureStarted<S>
+where
+    S: SenderTo<StartedRecv<S::Output, S::Error>>,
+{
+    type Output = S::Output;
+    type Error = S::Error;
+    type Stopped = S::Stopped;
+}
+
+/// The operation state of the consumer of [`EnsureStarted`].
+pub struct EnsureStartedOp<S, R>(WaiterOp<Shared<S>, R>)
+where
+    S: SenderTo<StartedRecv<S::Output, S::Error>>;
+
+unsafe impl<S, R> OperationState for EnsureStartedOp<S, R>
+where
+    S: SenderTo<StartedRecv<S::Output, S::Error>>,
+    R: Receiver<S::Output, S::Error>,
+{
+    unsafe fn start_by_ref(self: Pin<&mut Self>) {
+        // SAFETY: The consumer is pinned along with the operation, and started only
+        // once here.
+        unsafe { self.map_unchecked_mut(|op| &mut op.0).start() }
+    }
+}
+
+impl<S, R> SenderTo<R> for EnsureStarted<S>
+where
+    S: SenderTo<StartedRecv<S::Output, S::Error>>,
+    R: Receiver<S::Output, S::Error>,
+{
+    type Operation = EnsureStartedOp<S, R>;
+    type ConnectError = Infallible;
+
+    fn connect(mut self, receiver: R) -> impl InitPin<Self::Operation, Error = Self::ConnectError> {
+        let shared = self.0.take().expect(ONESHOT_COMPLETED);
+        init::with(move || EnsureStartedOp(WaiterOp::new(shared, receiver)))
+    }
+}
+
+/// Starts the sender right away in a heap-allocated operation state, and
+/// returns a sender for its eventual result.
+///
+/// The operation observes the stop token of a stop source in the shared state,
+/// which is requested if the returned sender is dropped without connecting.
+/// Since it may keep running after that, it must not borrow anything.
+pub fn ensure_started<S>(sender: S) -> Result<EnsureStarted<S>, S::ConnectError>
+where
+    S: SenderTo<StartedRecv<S::Output, S::Error>, Operation: Send + 'static>,
+    S::Output: Send + 'static,
+    S::Error: Send + 'static,
+{
+    let mut shared = Arc::<Shared<S>>::new_uninit();
+    let ptr = Arc::get_mut(&mut shared).unwrap().as_mut_ptr();
+    // SAFETY: The fields are initialized in place before the `Arc` is shared, and
+    // the receiver points to `core`, which is dropped after `op`. The reference
+    // held by the receiver is released when it is dropped, even if the connection
+    // fails.
+    unsafe {
+        let core = &raw mut (*ptr).core;
+        core.write(Core {
+            source: InPlaceStopSource::new(),
+            completion: OnceSlot::new(),
+            waiter: Callbacks::new(),
+        });
+        let owner = Arc::into_raw(shared.clone()).cast_mut().cast::<()>();
+        let recv = StartedRecv {
+            core: NonNull::new_unchecked(core),
+            release: Release {
+                owner: NonNull::new_unchecked(owner),
+                drop: release::<Shared<S>>,
+            },
+        };
+
+        let mut subslot = ManuallyDrop::new(DroppingSlot::new());
+        let subslot_ref = DropSlot::new_unchecked(&mut subslot);
+        let op = UnsafeCell::raw_get(&raw mut (*ptr).op);
+        match Uninit::from_raw(op).try_write_pin(sender.connect(recv), subslot_ref) {
+            Ok(p) => mem::forget(p),
+            Err(err) => {
+                core.drop_in_place();
+                return Err(err.error);
+            }
+        }
+
+        let shared = shared.assume_init();
+        // SAFETY: The operation lives in place in the `Arc` until it completes, and
+        // it is started only once here.
+        Pin::new_unchecked(&mut *shared.op.get()).start_by_ref();
+        Ok(EnsureStarted(Some(shared)))
+    }
+}
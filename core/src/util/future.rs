@@ -11,14 +11,20 @@ use core::{
 use placid::prelude::*;
 use spin::Mutex;
 
-use crate::{Receiver, basic::*};
+use crate::{
+    Receiver,
+    basic::*,
+    completion::{Completion, Yes},
+    env::{EmptyEnv, EnvOf, GetEnv, QueryStopToken},
+    stop::{EnvStopCallback, OnStop},
+};
 
 pub struct FutureExpr<F>(PhantomData<F>);
 
 struct FutureData<F, R>
 where
     F: Future + Send,
-    R: Receiver<F::Output> + Send,
+    R: Receiver<F::Output, Env: QueryStopToken + Send> + Send,
 {
     f: F,
     recv: Option<R>,
@@ -27,10 +33,11 @@ where
 struct FutureStateInner<F, R>
 where
     F: Future + Send,
-    R: Receiver<F::Output> + Send,
+    R: Receiver<F::Output, Env: QueryStopToken + Send> + Send,
 {
     inner: Mutex<Option<FutureData<F, R>>>,
     polls_again: AtomicBool,
+    stopped: AtomicBool,
 }
 
 /// ```rust,compile_fail
@@ -53,7 +60,7 @@ struct _TestSendInheritance;
 impl<F, R, T> FutureStateInner<F, R>
 where
     F: Future<Output = T> + Send,
-    R: Receiver<T> + Send,
+    R: Receiver<T, Env: QueryStopToken + Send> + Send,
 {
     // SAFETY on Wakers:
     //
@@ -75,49 +82,66 @@ where
         |data| {
             // SAFETY: Keeping the signature `fn wake(self: Arc<Self>)`
             let arc = unsafe { Arc::from_raw(data.cast::<Self>()) };
-            if !arc.try_poll() {
-                arc.polls_again.store(true, Release);
-            }
+            arc.wake();
         },
         |data| {
             // SAFETY: Keeping the signature `fn wake_by_ref(self: &Arc<Self>)`
             let arc = unsafe { ManuallyDrop::new(Arc::from_raw(data.cast::<Self>())) };
-            if !arc.try_poll() {
-                arc.polls_again.store(true, Release);
-            }
+            arc.wake();
         },
         // fn drop(self: Arc<Self>)
         // SAFETY: Arc::drop(self) decrements the strong count.
         |data| unsafe { Arc::decrement_strong_count(data.cast::<Self>()) },
     );
 
-    fn try_poll(self: &Arc<Self>) -> bool {
-        let Some(mut inner_opt) = self.inner.try_lock() else {
-            return false;
-        };
-        let Some(inner) = &mut *inner_opt else {
-            return false;
-        };
+    /// Polls the future on the current thread, or leaves it to the thread that
+    /// is polling it, which checks again after releasing the lock.
+    fn wake(self: &Arc<Self>) {
+        self.polls_again.store(true, Release);
+        while self.polls_again.load(Acquire) {
+            let Some(mut inner_opt) = self.inner.try_lock() else {
+                return;
+            };
+            let Some(inner) = &mut *inner_opt else {
+                return;
+            };
+            let Some(completion) = self.poll(inner) else {
+                continue;
+            };
 
+            // Drop the future before completing, since the receiver may drop the
+            // operation state. The stop callback is left to the operation state,
+            // since this may run from within it.
+            let recv = inner.recv.take().expect("future polled after completion");
+            *inner_opt = None;
+            drop(inner_opt);
+            match completion {
+                Completion::Value(output) => recv.set(output),
+                Completion::Error(error) => match error {},
+                Completion::Stopped => recv.set_stopped(),
+            }
+            return;
+        }
+    }
+
+    /// Polls the future as long as it is woken, until it is ready or a stop is
+    /// requested.
+    fn poll(self: &Arc<Self>, inner: &mut FutureData<F, R>) -> Option<Completion<T, Infallible>> {
         while self.polls_again.swap(false, Acquire) {
+            if self.stopped.load(Acquire) {
+                return Some(Completion::Stopped);
+            }
             // SAFETY: Arc<Self> is a valid waker.
             let waker =
                 unsafe { ManuallyDrop::new(Waker::new(Arc::as_ptr(self).cast(), &Self::VTABLE)) };
             let mut cx = Context::from_waker(&waker);
 
             // SAFETY: We don't move out `f`.
-            match unsafe { Pin::new_unchecked(&mut inner.f) }.poll(&mut cx) {
-                Poll::Ready(output) => {
-                    let recv = inner.recv.take().expect("future polled after completion");
-                    recv.set(output);
-                    *inner_opt = None;
-                    return true;
-                }
-                Poll::Pending => {}
+            if let Poll::Ready(output) = unsafe { Pin::new_unchecked(&mut inner.f) }.poll(&mut cx) {
+                return Some(Completion::Value(output));
             }
         }
-
-        true
+        None
     }
 }
 
@@ -126,42 +150,73 @@ where
     F: Future<Output = T> + Send,
 {
     type Output = T;
+    type Error = Infallible;
+    type Stopped = Yes;
+    type Attrs = EmptyEnv;
     type Data = F;
     type SubSenders = ();
+
+    fn attrs(_: &F, _: &()) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+/// The stop callback of [`Async`], which polls the future once more to see
+/// the stop.
+struct WakeOnStop<F, R>(Arc<FutureStateInner<F, R>>)
+where
+    F: Future + Send,
+    R: Receiver<F::Output, Env: QueryStopToken + Send> + Send;
+
+impl<F, R> OnStop for WakeOnStop<F, R>
+where
+    F: Future + Send,
+    R: Receiver<F::Output, Env: QueryStopToken + Send> + Send,
+{
+    fn on_stop(self) {
+        self.0.stopped.store(true, Release);
+        self.0.wake();
+    }
 }
 
-pub struct FutureState<F, R>(Arc<FutureStateInner<F, R>>)
+pub struct FutureState<F, R>
 where
     F: Future + Send,
-    R: Receiver<F::Output> + Send;
+    R: Receiver<F::Output, Env: QueryStopToken + Send> + Send,
+{
+    on_stop: EnvStopCallback<EnvOf<R>, WakeOnStop<F, R>>,
+    inner: Arc<FutureStateInner<F, R>>,
+}
 
 impl<F, R> Drop for FutureState<F, R>
 where
     F: Future + Send,
-    R: Receiver<F::Output> + Send,
+    R: Receiver<F::Output, Env: QueryStopToken + Send> + Send,
 {
     fn drop(&mut self) {
         // Drop the future and receiver to cancel the operation, and make sure
         // they don't escape their lifetimes.
-        *self.0.inner.lock() = None;
+        *self.inner.inner.lock() = None;
     }
 }
 
 impl<F, R, T> SenderExprTo<R> for FutureExpr<F>
 where
     F: Future<Output = T> + Send,
-    R: Receiver<T> + Send,
+    R: Receiver<T, Env: QueryStopToken + Send> + Send,
 {
     type State = FutureState<F, R>;
-    type Error = Infallible;
-    type CreateState = impl InitPin<Self::State, Error = Self::Error>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
 
     fn create_state(f: Self::Data, _: &mut (), recv: R) -> Self::CreateState {
-        init::with(|| {
-            FutureState(Arc::new(FutureStateInner {
+        init::with(|| FutureState {
+            on_stop: EnvStopCallback::new(),
+            inner: Arc::new(FutureStateInner {
                 inner: Mutex::new(Some(FutureData { f, recv: Some(recv) })),
-                polls_again: AtomicBool::new(true),
-            }))
+                polls_again: AtomicBool::new(false),
+                stopped: AtomicBool::new(false),
+            }),
         })
     }
 
@@ -169,16 +224,37 @@ where
     where
         State<Self, R>: ConnectAll<Self, R>,
     {
-        state.state_mut().0.try_poll();
+        let state = state.state_mut().into_ref().get_ref();
+        let this = &state.inner;
+        if let Some(inner) = &mut *this.inner.lock() {
+            let env = inner
+                .recv
+                .as_ref()
+                .expect("future polled after completion")
+                .get_env();
+            // SAFETY: The state is pinned and started only once, and the callback
+            // refers to nothing but its own clone of `this`. If it runs inline, the
+            // lock we hold defers the poll to below.
+            unsafe { state.on_stop.register(env, WakeOnStop(Arc::clone(this))) };
+        }
+        this.wake();
     }
 
     fn complete(_: StateRef<'_, Self, R>, value: tsum::Sum<()>) {
         value.unreachable();
     }
+
+    fn error(_: StateRef<'_, Self, R>, error: tsum::Sum<()>) {
+        error.unreachable();
+    }
 }
 
 pub type Async<F> = BasicSender<FutureExpr<F>>;
 
+/// Polls the future to completion on whichever thread wakes it.
+///
+/// The future is dropped once a stop is requested, and the operation completes
+/// with a stopped signal instead.
 pub fn async_<F, T>(fut: F) -> Async<F>
 where
     F: Future<Output = T> + Send,
@@ -192,19 +268,40 @@ mod tests {
 
     use placid::pown;
 
-    use crate::{OperationState, Receiver, SenderTo, util::*};
+    use crate::{
+        OperationState, Receiver, SenderTo,
+        env::{EmptyEnv, GetEnv},
+        util::*,
+    };
 
     struct DummyReceiver;
 
-    impl<T: core::fmt::Debug> Receiver<T> for DummyReceiver {
+    impl GetEnv for DummyReceiver {
+        type Env = EmptyEnv;
+
+        fn get_env(&self) -> EmptyEnv {
+            EmptyEnv
+        }
+    }
+
+    impl<T: core::fmt::Debug, E: core::fmt::Debug> Receiver<T, E> for DummyReceiver {
         fn set(self, value: T) {
             std::println!("received: {:?}", value);
         }
+
+        fn set_error(self, error: E) {
+            std::println!("error: {:?}", error);
+        }
+
+        fn set_stopped(self) {
+            STOPPED.set(true);
+        }
     }
 
     std::thread_local! {
         static WAKER: Cell<Option<Waker>> = const { Cell::new(None) };
         static DROPPED: Cell<bool> = const { Cell::new(false) };
+        static STOPPED: Cell<bool> = const { Cell::new(false) };
     }
 
     struct TestFuture(Option<i32>);
@@ -252,6 +349,8 @@ mod tests {
             OperationState::start(op);
         }
         assert!(DROPPED.replace(false));
+        // The receiver is dropped along with the operation without completion.
+        assert!(!STOPPED.replace(false));
         let waker = WAKER.replace(None).unwrap();
         waker.wake();
     }
@@ -0,0 +1,66 @@
+use core::{convert::Infallible, pin::Pin};
+
+use placid::prelude::*;
+
+use crate::{
+    Receiver, Scheduler, basic::*, completion::No, env::SchedulerAttrs, util::ONESHOT_COMPLETED,
+};
+
+/// A scheduler that runs the work in place, on the thread that starts it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct InlineScheduler;
+
+pub struct InlineExpr;
+
+impl SenderExpr for InlineExpr {
+    type Output = ();
+    type Error = Infallible;
+    type Stopped = No;
+    type Attrs = SchedulerAttrs<InlineScheduler>;
+    type Data = ();
+    type SubSenders = ();
+
+    fn attrs(_: &(), _: &()) -> Self::Attrs {
+        SchedulerAttrs(InlineScheduler)
+    }
+}
+
+pub struct InlineState<R>(Option<R>);
+
+impl<R> Unpin for InlineState<R> {}
+
+impl<R: Receiver<()>> SenderExprTo<R> for InlineExpr {
+    type State = InlineState<R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(_: (), _: &mut (), recv: R) -> Self::CreateState {
+        init::value(InlineState(Some(recv)))
+    }
+
+    fn start(state: Pin<&mut State<Self, R>>, _: Pin<&mut ConnectAllOps<Self, R>>)
+    where
+        State<Self, R>: ConnectAll<Self, R>,
+    {
+        let recv = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set(());
+    }
+
+    fn complete(_: Pin<&mut State<Self, R>>, value: tsum::Sum<()>) {
+        value.unreachable();
+    }
+
+    fn error(_: Pin<&mut State<Self, R>>, error: tsum::Sum<()>) {
+        error.unreachable();
+    }
+}
+
+pub type Inline = BasicSender<InlineExpr>;
+
+impl Scheduler for InlineScheduler {
+    type Task = Inline;
+
+    fn schedule(&self) -> Inline {
+        BasicSender::new((), ())
+    }
+}
@@ -0,0 +1,159 @@
+use core::{convert::Infallible, marker::PhantomData, pin::Pin};
+
+use placid::prelude::*;
+use tsum::{Sum, T, t};
+
+use crate::{
+    Receiver, Sender, SenderTo,
+    basic::*,
+    completion::FlagOr,
+    env::EmptyEnv,
+    util::{ONESHOT_COMPLETED, TryConnectRecv, and_then::AndThenState},
+};
+
+pub struct LetErrorExpr<S, F>(PhantomData<(S, F)>);
+
+impl<S, F, T> SenderExpr for LetErrorExpr<S, F>
+where
+    S: Sender,
+    F: FnOnce(S::Error) -> T,
+    T: Sender<Output = S::Output>,
+{
+    type Output = S::Output;
+    type Error = T::Error;
+    type Stopped = FlagOr<S::Stopped, T::Stopped>;
+    type Attrs = EmptyEnv;
+    type Data = F;
+    type SubSenders = T![S];
+
+    fn attrs(_: &F, _: &T![S]) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<S, F, T, R> SenderExprTo<R> for LetErrorExpr<S, F>
+where
+    S: Sender,
+    F: FnOnce(S::Error) -> T,
+    T: SenderTo<
+            TryConnectRecv<R>,
+            Operation: Sized,
+            Output = S::Output,
+            ConnectError: Into<T::Error>,
+        >,
+    R: Receiver<S::Output, T::Error>,
+{
+    type State = AndThenState<T::Operation, F, R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(data: Self::Data, _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
+        AndThenState::new(data, recv)
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let (_, recv) = state.state_mut().take().expect(ONESHOT_COMPLETED);
+        recv.set(value.into_inner());
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let mut state = state.state_mut();
+        let (func, recv) = state.as_mut().take().expect(ONESHOT_COMPLETED);
+        state.start_next(func(error.into_inner()), recv);
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        if let Some((_, recv)) = state.state_mut().take() {
+            recv.set_stopped();
+        }
+    }
+}
+
+pub type LetError<S, F> = BasicSender<LetErrorExpr<S, F>>;
+
+/// Continues with the sender created from the error of the sender, e.g. to
+/// fall back to another source.
+///
+/// A failed connection of the continuation completes with the connect error
+/// as in [`and_then`](super::and_then).
+pub const fn let_error<S, F, T>(sender: S, func: F) -> LetError<S, F>
+where
+    S: Sender,
+    F: FnOnce(S::Error) -> T,
+    T: Sender<Output = S::Output>,
+{
+    BasicSender::new(func, t![sender])
+}
+
+pub struct LetStoppedExpr<S, F>(PhantomData<(S, F)>);
+
+impl<S, F, T> SenderExpr for LetStoppedExpr<S, F>
+where
+    S: Sender,
+    F: FnOnce() -> T,
+    T: Sender<Output = S::Output, Error = S::Error>,
+{
+    type Output = S::Output;
+    type Error = S::Error;
+    type Stopped = T::Stopped;
+    type Attrs = EmptyEnv;
+    type Data = F;
+    type SubSenders = T![S];
+
+    fn attrs(_: &F, _: &T![S]) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<S, F, T, R> SenderExprTo<R> for LetStoppedExpr<S, F>
+where
+    S: Sender,
+    F: FnOnce() -> T,
+    T: SenderTo<
+            TryConnectRecv<R>,
+            Operation: Sized,
+            Output = S::Output,
+            Error = S::Error,
+            ConnectError: Into<S::Error>,
+        >,
+    R: Receiver<S::Output, S::Error>,
+{
+    type State = AndThenState<T::Operation, F, R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(data: Self::Data, _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
+        AndThenState::new(data, recv)
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let (_, recv) = state.state_mut().take().expect(ONESHOT_COMPLETED);
+        recv.set(value.into_inner());
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let (_, recv) = state.state_mut().take().expect(ONESHOT_COMPLETED);
+        recv.set_error(error.into_inner());
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        let mut state = state.state_mut();
+        let (func, recv) = state.as_mut().take().expect(ONESHOT_COMPLETED);
+        state.start_next(func(), recv);
+    }
+}
+
+pub type LetStopped<S, F> = BasicSender<LetStoppedExpr<S, F>>;
+
+/// Continues with the sender created on the stopped signal of the sender.
+///
+/// A failed connection of the continuation completes with the connect error
+/// as in [`and_then`](super::and_then).
+pub const fn let_stopped<S, F, T>(sender: S, func: F) -> LetStopped<S, F>
+where
+    S: Sender,
+    F: FnOnce() -> T,
+    T: Sender<Output = S::Output, Error = S::Error>,
+{
+    BasicSender::new(func, t![sender])
+}
@@ -0,0 +1,135 @@
+use core::{convert::Infallible, marker::PhantomData, pin::Pin};
+
+use pin_project::pin_project;
+use placid::prelude::*;
+use tsum::{Sum, T, t};
+
+use crate::{
+    Receiver, Sender, SenderTo,
+    basic::*,
+    completion::FlagOr,
+    env::EmptyEnv,
+    util::{ONESHOT_COMPLETED, TryConnectRecv, and_then::AndThenState},
+};
+
+/// A continuation of [`let_value`], which creates a sender that may borrow the
+/// value for `'a`.
+///
+/// The output and error types of the sender must not depend on `'a`, so that
+/// the borrow cannot escape the operation state. It is implemented for any
+/// function with a matching signature. Since closures cannot return types that
+/// borrow from their arguments, use a named function in that case.
+pub trait LetFn<'a, V: 'a, T, E>: FnOnce(&'a mut V) -> Self::Next {
+    type Next: Sender<Output = T, Error = E>;
+}
+
+impl<'a, V: 'a, T, E, F, N> LetFn<'a, V, T, E> for F
+where
+    F: FnOnce(&'a mut V) -> N,
+    N: Sender<Output = T, Error = E>,
+{
+    type Next = N;
+}
+
+/// The sender created by the continuation of [`LetValue`] for a borrow of `'a`.
+type NextOf<'a, F, V, T, E> = <F as LetFn<'a, V, T, E>>::Next;
+
+/// The expression of [`let_value`], whose continuation is stored in the
+/// operation state as if it borrowed the value for `'v`.
+///
+/// The continuation must connect for any borrow, so that nothing can rely on
+/// `'v` in particular.
+pub struct LetValueExpr<'v, S, F, T>(PhantomData<(&'v (), S, F, T)>);
+
+#[derive(InitPin)]
+#[pin_project]
+pub struct LetValueState<V, O, F, R> {
+    // `next` must come before `value`, since its operation borrows from it.
+    #[pin]
+    next: AndThenState<O, F, R>,
+    value: Option<V>,
+}
+
+impl<'v, S, F, T> SenderExpr for LetValueExpr<'v, S, F, T>
+where
+    S: Sender<Output: 'v>,
+    F: for<'a> LetFn<'a, S::Output, T, S::Error>,
+{
+    type Output = T;
+    type Error = S::Error;
+    type Stopped = FlagOr<S::Stopped, <NextOf<'v, F, S::Output, T, S::Error> as Sender>::Stopped>;
+    type Attrs = EmptyEnv;
+    type Data = F;
+    type SubSenders = T![S];
+
+    fn attrs(_: &F, _: &T![S]) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<'v, S, F, T, R> SenderExprTo<R> for LetValueExpr<'v, S, F, T>
+where
+    S: Sender<Output: 'v>,
+    F: for<'a> LetFn<'a, S::Output, T, S::Error>,
+    for<'a> NextOf<'a, F, S::Output, T, S::Error>:
+        SenderTo<TryConnectRecv<R>, Operation: Sized, ConnectError: Into<S::Error>>,
+    R: Receiver<T, S::Error>,
+{
+    type State = LetValueState<
+        S::Output,
+        <NextOf<'v, F, S::Output, T, S::Error> as SenderTo<TryConnectRecv<R>>>::Operation,
+        F,
+        R,
+    >;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(data: Self::Data, _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
+        init_pin!(LetValueState {
+            next: AndThenState::new(data, recv),
+            value: || None,
+        })
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let state = state.state_mut().project();
+        let mut next = state.next;
+        let (func, recv) = next.as_mut().take().expect(ONESHOT_COMPLETED);
+        let value: *mut S::Output = state.value.insert(value.into_inner());
+        // SAFETY: The value is pinned in the state and never taken out, and the
+        // continuation is dropped before it. The borrow cannot escape, since the
+        // continuation connects for any lifetime and its completions do not
+        // depend on it.
+        let next_snd: NextOf<'v, F, S::Output, T, S::Error> = func(unsafe { &mut *value });
+        next.start_next(next_snd, recv);
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let state = state.state_mut().project();
+        let (_, recv) = state.next.take().expect(ONESHOT_COMPLETED);
+        recv.set_error(error.into_inner());
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        let state = state.state_mut().project();
+        if let Some((_, recv)) = state.next.take() {
+            recv.set_stopped();
+        }
+    }
+}
+
+pub type LetValue<'v, S, F, T> = BasicSender<LetValueExpr<'v, S, F, T>>;
+
+/// Stores the output of the sender in the operation state, and continues with
+/// the sender created from a mutable reference to it.
+///
+/// Unlike [`and_then`](super::and_then), the continuation may borrow the value
+/// for as long as it runs. Like it, a failed connection of the continuation
+/// completes with the connect error.
+pub const fn let_value<'v, S, F, T>(sender: S, func: F) -> LetValue<'v, S, F, T>
+where
+    S: Sender<Output: 'v>,
+    F: for<'a> LetFn<'a, S::Output, T, S::Error>,
+{
+    BasicSender::new(func, t![sender])
+}
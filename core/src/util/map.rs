@@ -3,7 +3,13 @@ use core::{convert::Infallible, marker::PhantomData, pin::Pin};
 use placid::prelude::*;
 use tsum::{Sum, T, t};
 
-use crate::{Receiver, Sender, basic::*, util::ONESHOT_COMPLETED};
+use crate::{
+    Receiver, Sender,
+    basic::*,
+    completion::Yes,
+    env::{EnvOf, GetEnv},
+    util::ONESHOT_COMPLETED,
+};
 
 pub struct MapExpr<S, F>(PhantomData<(S, F)>);
 
@@ -13,11 +19,19 @@ where
     F: FnOnce(S::Output) -> T,
 {
     type Output = T;
+    type Error = S::Error;
+    type Stopped = S::Stopped;
+    type Attrs = EnvOf<S>;
     type Data = F;
     type SubSenders = T![S];
+    type Customizable = Yes;
+
+    fn attrs(_: &F, sub_senders: &T![S]) -> EnvOf<S> {
+        sub_senders.0.get_env()
+    }
 }
 
-pub struct MapState<T>(Option<T>);
+pub struct MapState<T>(pub(super) Option<T>);
 
 impl<F> Unpin for MapState<F> {}
 
@@ -25,11 +39,11 @@ impl<S, F, T, R> SenderExprTo<R> for MapExpr<S, F>
 where
     F: FnOnce(S::Output) -> T,
     S: Sender,
-    R: Receiver<T>,
+    R: Receiver<T, S::Error>,
 {
     type State = MapState<(F, R)>;
-    type Error = Infallible;
-    type CreateState = impl InitPin<Self::State, Error = Self::Error>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
 
     fn create_state(data: Self::Data, _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
         init::with(move || MapState(Some((data, recv))))
@@ -40,6 +54,17 @@ where
         let result = func(value.into_inner());
         recv.set(result);
     }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let (_, recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set_error(error.into_inner());
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        if let Some((_, recv)) = state.state_mut().0.take() {
+            recv.set_stopped();
+        }
+    }
 }
 
 pub type Map<S, F> = BasicSender<MapExpr<S, F>>;
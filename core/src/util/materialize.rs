@@ -0,0 +1,125 @@
+use core::{convert::Infallible, marker::PhantomData, pin::Pin};
+
+use placid::prelude::*;
+use tsum::{Sum, T, t};
+
+use crate::{
+    Receiver, Sender,
+    basic::*,
+    completion::{Completion, No, Yes},
+    env::{EnvOf, GetEnv},
+    util::ONESHOT_COMPLETED,
+};
+
+pub struct MaterializeState<R>(Option<R>);
+
+impl<R> Unpin for MaterializeState<R> {}
+
+pub struct MaterializeExpr<S>(PhantomData<S>);
+
+impl<S: Sender> SenderExpr for MaterializeExpr<S> {
+    type Output = Completion<S::Output, S::Error>;
+    type Error = Infallible;
+    type Stopped = No;
+    type Attrs = EnvOf<S>;
+    type Data = ();
+    type SubSenders = T![S];
+
+    fn attrs(_: &(), sub_senders: &T![S]) -> EnvOf<S> {
+        sub_senders.0.get_env()
+    }
+}
+
+impl<S, R> SenderExprTo<R> for MaterializeExpr<S>
+where
+    S: Sender,
+    R: Receiver<Completion<S::Output, S::Error>>,
+{
+    type State = MaterializeState<R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(_: (), _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
+        init::with(move || MaterializeState(Some(recv)))
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let recv = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set(Completion::Value(value.into_inner()));
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let recv = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set(Completion::Error(error.into_inner()));
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        let recv = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set(Completion::Stopped);
+    }
+}
+
+pub type Materialize<S> = BasicSender<MaterializeExpr<S>>;
+
+/// Reifies any completion of the sender into a [`Completion`] value.
+pub const fn materialize<S: Sender>(sender: S) -> Materialize<S> {
+    BasicSender::new((), t![sender])
+}
+
+pub struct DematerializeExpr<S>(PhantomData<S>);
+
+impl<S, T, E> SenderExpr for DematerializeExpr<S>
+where
+    S: Sender<Output = Completion<T, E>, Error = Infallible>,
+{
+    type Output = T;
+    type Error = E;
+    type Stopped = Yes;
+    type Attrs = EnvOf<S>;
+    type Data = ();
+    type SubSenders = T![S];
+
+    fn attrs(_: &(), sub_senders: &T![S]) -> EnvOf<S> {
+        sub_senders.0.get_env()
+    }
+}
+
+impl<S, T, E, R> SenderExprTo<R> for DematerializeExpr<S>
+where
+    S: Sender<Output = Completion<T, E>, Error = Infallible>,
+    R: Receiver<T, E>,
+{
+    type State = MaterializeState<R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(_: (), _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
+        init::with(move || MaterializeState(Some(recv)))
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let recv = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        value.into_inner().deliver(recv);
+    }
+
+    fn error(_: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        match error.into_inner() {}
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        if let Some(recv) = state.state_mut().0.take() {
+            recv.set_stopped();
+        }
+    }
+}
+
+pub type Dematerialize<S> = BasicSender<DematerializeExpr<S>>;
+
+/// Turns the [`Completion`] value of the sender back into the completion it
+/// reifies.
+pub const fn dematerialize<S, T, E>(sender: S) -> Dematerialize<S>
+where
+    S: Sender<Output = Completion<T, E>, Error = Infallible>,
+{
+    BasicSender::new((), t![sender])
+}
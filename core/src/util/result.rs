@@ -0,0 +1,290 @@
+use core::{convert::Infallible, marker::PhantomData, pin::Pin};
+
+use placid::prelude::*;
+use tsum::{Sum, T, t};
+
+use crate::{
+    Receiver, Sender, SenderTo,
+    basic::*,
+    completion::FlagOr,
+    env::{EmptyEnv, EnvOf, GetEnv},
+    util::{ONESHOT_COMPLETED, TryConnectRecv, and_then::AndThenState, map::MapState},
+};
+
+pub struct MapOkExpr<S, F>(PhantomData<(S, F)>);
+
+impl<S, F, T, E, U> SenderExpr for MapOkExpr<S, F>
+where
+    S: Sender<Output = Result<T, E>>,
+    F: FnOnce(T) -> U,
+{
+    type Output = Result<U, E>;
+    type Error = S::Error;
+    type Stopped = S::Stopped;
+    type Attrs = EnvOf<S>;
+    type Data = F;
+    type SubSenders = T![S];
+
+    fn attrs(_: &F, sub_senders: &T![S]) -> EnvOf<S> {
+        sub_senders.0.get_env()
+    }
+}
+
+impl<S, F, T, E, U, R> SenderExprTo<R> for MapOkExpr<S, F>
+where
+    S: Sender<Output = Result<T, E>>,
+    F: FnOnce(T) -> U,
+    R: Receiver<Result<U, E>, S::Error>,
+{
+    type State = MapState<(F, R)>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(data: Self::Data, _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
+        init::with(move || MapState(Some((data, recv))))
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let (func, recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set(value.into_inner().map(func));
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let (_, recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set_error(error.into_inner());
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        if let Some((_, recv)) = state.state_mut().0.take() {
+            recv.set_stopped();
+        }
+    }
+}
+
+pub type MapOk<S, F> = BasicSender<MapOkExpr<S, F>>;
+
+/// Maps the `Ok` output of the sender, passing `Err` through.
+pub const fn map_ok<S, F, T, E, U>(sender: S, func: F) -> MapOk<S, F>
+where
+    S: Sender<Output = Result<T, E>>,
+    F: FnOnce(T) -> U,
+{
+    BasicSender::new(func, t![sender])
+}
+
+pub struct MapErrExpr<S, F>(PhantomData<(S, F)>);
+
+impl<S, F, T, E, U> SenderExpr for MapErrExpr<S, F>
+where
+    S: Sender<Output = Result<T, E>>,
+    F: FnOnce(E) -> U,
+{
+    type Output = Result<T, U>;
+    type Error = S::Error;
+    type Stopped = S::Stopped;
+    type Attrs = EnvOf<S>;
+    type Data = F;
+    type SubSenders = T![S];
+
+    fn attrs(_: &F, sub_senders: &T![S]) -> EnvOf<S> {
+        sub_senders.0.get_env()
+    }
+}
+
+impl<S, F, T, E, U, R> SenderExprTo<R> for MapErrExpr<S, F>
+where
+    S: Sender<Output = Result<T, E>>,
+    F: FnOnce(E) -> U,
+    R: Receiver<Result<T, U>, S::Error>,
+{
+    type State = MapState<(F, R)>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(data: Self::Data, _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
+        init::with(move || MapState(Some((data, recv))))
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let (func, recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set(value.into_inner().map_err(func));
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let (_, recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set_error(error.into_inner());
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        if let Some((_, recv)) = state.state_mut().0.take() {
+            recv.set_stopped();
+        }
+    }
+}
+
+pub type MapErr<S, F> = BasicSender<MapErrExpr<S, F>>;
+
+/// Maps the `Err` output of the sender, passing `Ok` through.
+pub const fn map_err<S, F, T, E, U>(sender: S, func: F) -> MapErr<S, F>
+where
+    S: Sender<Output = Result<T, E>>,
+    F: FnOnce(E) -> U,
+{
+    BasicSender::new(func, t![sender])
+}
+
+pub struct AndThenOkExpr<S, F>(PhantomData<(S, F)>);
+
+impl<S, F, T, E, N, U> SenderExpr for AndThenOkExpr<S, F>
+where
+    S: Sender<Output = Result<T, E>>,
+    F: FnOnce(T) -> N,
+    N: Sender<Output = Result<U, E>, Error = S::Error>,
+{
+    type Output = Result<U, E>;
+    type Error = S::Error;
+    type Stopped = FlagOr<S::Stopped, N::Stopped>;
+    type Attrs = EmptyEnv;
+    type Data = F;
+    type SubSenders = T![S];
+
+    fn attrs(_: &F, _: &T![S]) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<S, F, T, E, N, U, R> SenderExprTo<R> for AndThenOkExpr<S, F>
+where
+    S: Sender<Output = Result<T, E>>,
+    F: FnOnce(T) -> N,
+    N: SenderTo<
+            TryConnectRecv<R>,
+            Operation: Sized,
+            Output = Result<U, E>,
+            Error = S::Error,
+            ConnectError: Into<S::Error>,
+        >,
+    R: Receiver<Result<U, E>, S::Error>,
+{
+    type State = AndThenState<N::Operation, F, R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(data: Self::Data, _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
+        AndThenState::new(data, recv)
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let mut state = state.state_mut();
+        let (func, recv) = state.as_mut().take().expect(ONESHOT_COMPLETED);
+        match value.into_inner() {
+            Ok(value) => state.start_next(func(value), recv),
+            Err(error) => recv.set(Err(error)),
+        }
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let (_, recv) = state.state_mut().take().expect(ONESHOT_COMPLETED);
+        recv.set_error(error.into_inner());
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        if let Some((_, recv)) = state.state_mut().take() {
+            recv.set_stopped();
+        }
+    }
+}
+
+pub type AndThenOk<S, F> = BasicSender<AndThenOkExpr<S, F>>;
+
+/// Continues with the sender created from the `Ok` output of the sender, or
+/// completes with the `Err` output right away without creating it.
+///
+/// A failed connection of the continuation completes with the connect error
+/// as in [`and_then`](super::and_then).
+pub const fn and_then_ok<S, F, T, E, N, U>(sender: S, func: F) -> AndThenOk<S, F>
+where
+    S: Sender<Output = Result<T, E>>,
+    F: FnOnce(T) -> N,
+    N: Sender<Output = Result<U, E>, Error = S::Error>,
+{
+    BasicSender::new(func, t![sender])
+}
+
+pub struct OrElseExpr<S, F>(PhantomData<(S, F)>);
+
+impl<S, F, T, E, N, U> SenderExpr for OrElseExpr<S, F>
+where
+    S: Sender<Output = Result<T, E>>,
+    F: FnOnce(E) -> N,
+    N: Sender<Output = Result<T, U>, Error = S::Error>,
+{
+    type Output = Result<T, U>;
+    type Error = S::Error;
+    type Stopped = FlagOr<S::Stopped, N::Stopped>;
+    type Attrs = EmptyEnv;
+    type Data = F;
+    type SubSenders = T![S];
+
+    fn attrs(_: &F, _: &T![S]) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<S, F, T, E, N, U, R> SenderExprTo<R> for OrElseExpr<S, F>
+where
+    S: Sender<Output = Result<T, E>>,
+    F: FnOnce(E) -> N,
+    N: SenderTo<
+            TryConnectRecv<R>,
+            Operation: Sized,
+            Output = Result<T, U>,
+            Error = S::Error,
+            ConnectError: Into<S::Error>,
+        >,
+    R: Receiver<Result<T, U>, S::Error>,
+{
+    type State = AndThenState<N::Operation, F, R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(data: Self::Data, _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
+        AndThenState::new(data, recv)
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let mut state = state.state_mut();
+        let (func, recv) = state.as_mut().take().expect(ONESHOT_COMPLETED);
+        match value.into_inner() {
+            Ok(value) => recv.set(Ok(value)),
+            Err(error) => state.start_next(func(error), recv),
+        }
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let (_, recv) = state.state_mut().take().expect(ONESHOT_COMPLETED);
+        recv.set_error(error.into_inner());
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        if let Some((_, recv)) = state.state_mut().take() {
+            recv.set_stopped();
+        }
+    }
+}
+
+pub type OrElse<S, F> = BasicSender<OrElseExpr<S, F>>;
+
+/// Continues with the sender created from the `Err` output of the sender, or
+/// completes with the `Ok` output right away without creating it.
+///
+/// A failed connection of the continuation completes with the connect error
+/// as in [`and_then`](super::and_then).
+pub const fn or_else<S, F, T, E, N, U>(sender: S, func: F) -> OrElse<S, F>
+where
+    S: Sender<Output = Result<T, E>>,
+    F: FnOnce(E) -> N,
+    N: Sender<Output = Result<T, U>, Error = S::Error>,
+{
+    BasicSender::new(func, t![sender])
+}
@@ -0,0 +1,249 @@
+use alloc::sync::Arc;
+use core::{cell::UnsafeCell, convert::Infallible, marker::PhantomPinned, pin::Pin, ptr::NonNull};
+
+use placid::prelude::*;
+use spin::Mutex;
+
+use crate::{
+    OperationState, Receiver, Sender, SenderTo,
+    completion::No,
+    env::{EmptyEnv, GetEnv, StopEnv},
+    stop::{CallbackNode, Callbacks, InPlaceStopSource},
+    util::{
+        ONESHOT_COMPLETED,
+        detached::{HeapOp, start_in_heap},
+    },
+};
+
+struct ScopeState {
+    source: InPlaceStopSource,
+    count: Mutex<usize>,
+    // The joins that started with operations outstanding, linked in place in
+    // their operation states. Locked before `count` if both are.
+    joiners: Callbacks,
+}
+
+impl ScopeState {
+    fn enter(&self) {
+        *self.count.lock() += 1;
+    }
+
+    fn leave(&self) {
+        let mut count = self.count.lock();
+        *count -= 1;
+        if *count > 0 {
+            return;
+        }
+        drop(count);
+        // The joiners are completed outside of the lock, since they may spawn
+        // more work into the scope, after which the others keep waiting.
+        self.joiners.execute_while(|| *self.count.lock() == 0);
+    }
+}
+
+/// Counts the spawned operation as finished on drop.
+struct Leave(Arc<ScopeState>);
+
+impl Drop for Leave {
+    fn drop(&mut self) {
+        self.0.leave();
+    }
+}
+
+/// The receiver of an operation spawned into a [`CountingScope`].
+pub struct ScopeRecv {
+    // `_op` must come first, so that the operation state is freed before it is
+    // counted as finished.
+    _op: HeapOp,
+    scope: Leave,
+}
+
+impl GetEnv for ScopeRecv {
+    type Env = StopEnv<EmptyEnv>;
+
+    fn get_env(&self) -> StopEnv<EmptyEnv> {
+        // SAFETY: The stop source lives in the scope state, which is kept alive until
+        // the operation state is freed.
+        unsafe { StopEnv::new(EmptyEnv, NonNull::from_ref(&self.scope.0.source)) }
+    }
+}
+
+impl<T> Receiver<T, Infallible> for ScopeRecv {
+    fn set(self, _: T) {}
+
+    fn set_error(self, error: Infallible) {
+        match error {}
+    }
+
+    fn set_stopped(self) {}
+}
+
+/// An async scope that counts the operations spawned into it, so that they can
+/// be stopped or joined all at once.
+///
+/// The spawned operations observe the stop token of a stop source in the
+/// scope, which is requested by [`CountingScope::request_stop`].
+///
+/// Like the `counting_scope` of P3149, the scope must be joined before it is
+/// dropped, so that no spawned operation outlives it. Dropping it with
+/// outstanding operations requests them to stop, and panics in debug builds.
+/// Request a stop beforehand to join sooner.
+///
+/// Without the `std` feature, a join that starts with operations outstanding
+/// must not be dropped from within its own receiver, for the same reason as
+/// [`InPlaceStopCallback`](crate::stop::InPlaceStopCallback).
+pub struct CountingScope {
+    state: Arc<ScopeState>,
+}
+
+impl Default for CountingScope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CountingScope {
+    pub fn new() -> Self {
+        CountingScope {
+            state: Arc::new(ScopeState {
+                source: InPlaceStopSource::new(),
+                count: Mutex::new(0),
+                joiners: Callbacks::new(),
+            }),
+        }
+    }
+
+    /// Connects the sender and starts it in a heap-allocated operation state,
+    /// which is freed once the operation completes.
+    ///
+    /// The values are discarded, and errors must have been handled beforehand.
+    pub fn spawn<S>(&self, sender: S) -> Result<(), S::ConnectError>
+    where
+        S: SenderTo<ScopeRecv, Error = Infallible, Operation: Send + 'static>,
+    {
+        self.state.enter();
+        // SAFETY: The operation is `'static`.
+        unsafe {
+            start_in_heap(sender, |op| ScopeRecv {
+                _op: op,
+                scope: Leave(self.state.clone()),
+            })
+        }
+    }
+
+    /// Requests all the outstanding and future operations in the scope to stop.
+    pub fn request_stop(&self) -> bool {
+        self.state.source.request_stop()
+    }
+
+    /// Returns a sender that completes once all the operations spawned into
+    /// the scope have completed.
+    pub fn join(&self) -> Join {
+        Join(self.state.clone())
+    }
+}
+
+impl Drop for CountingScope {
+    fn drop(&mut self) {
+        let count = *self.state.count.lock();
+        if count > 0 {
+            // The operations keep the state alive, so they are left to finish
+            // on their own, as soon as possible.
+            self.state.source.request_stop();
+        }
+        debug_assert_eq!(
+            count, 0,
+            "counting scope dropped before its operations are joined"
+        );
+    }
+}
+
+/// A sender that completes once the operations spawned into a
+/// [`CountingScope`] have completed.
+pub struct Join(Arc<ScopeState>);
+
+impl GetEnv for Join {
+    type Env = EmptyEnv;
+
+    fn get_env(&self) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl Sender for Join {
+    type Output = ();
+    type Error = Infallible;
+    type Stopped = No;
+}
+
+/// The operation state of [`Join`].
+#[repr(C)]
+pub struct JoinOp<R> {
+    // `node` must be the first field so that it can be cast back to `Self`.
+    node: CallbackNode,
+    state: Arc<ScopeState>,
+    // Taken by whoever completes it, either on starting or once the node is
+    // unlinked for execution.
+    recv: UnsafeCell<Option<R>>,
+    _marker: PhantomPinned,
+}
+
+// SAFETY: The receiver is only accessed by the operation, or by the execution
+// of the node, which the operation waits for on dropping.
+unsafe impl<R: Send> Send for JoinOp<R> {}
+// SAFETY: Same as above.
+unsafe impl<R: Send> Sync for JoinOp<R> {}
+
+impl<R: Receiver<()>> JoinOp<R> {
+    /// # Safety
+    ///
+    /// `node` must point to the `node` field of a valid `Self`, and this
+    /// function must be called at most once for it.
+    unsafe fn execute(node: NonNull<CallbackNode>) {
+        let this = node.cast::<Self>().as_ptr();
+        // SAFETY: The receiver is taken out only once.
+        let recv = unsafe { (*(*this).recv.get()).take() };
+        recv.expect(ONESHOT_COMPLETED).set(());
+    }
+}
+
+impl<R> Drop for JoinOp<R> {
+    fn drop(&mut self) {
+        // The receiver is dropped without completion along with the operation if
+        // it is still waiting.
+        // SAFETY: The node is only ever linked in the list of the scope.
+        unsafe { self.state.joiners.deregister(&self.node) };
+    }
+}
+
+unsafe impl<R: Receiver<()>> OperationState for JoinOp<R> {
+    unsafe fn start_by_ref(self: Pin<&mut Self>) {
+        let this = self.into_ref().get_ref();
+        let mut joiners = this.state.joiners.lock();
+        if *this.state.count.lock() > 0 {
+            // SAFETY: The operation is pinned, and it deregisters the node on
+            // dropping.
+            unsafe { joiners.push_back(NonNull::from_ref(&this.node)) };
+            return;
+        }
+        drop(joiners);
+        // SAFETY: The node is not linked, so we are the only one accessing the
+        // receiver.
+        let recv = unsafe { (*this.recv.get()).take() };
+        recv.expect(ONESHOT_COMPLETED).set(());
+    }
+}
+
+impl<R: Receiver<()>> SenderTo<R> for Join {
+    type Operation = JoinOp<R>;
+    type ConnectError = Infallible;
+
+    fn connect(self, receiver: R) -> impl InitPin<Self::Operation, Error = Self::ConnectError> {
+        init::with(move || JoinOp {
+            node: CallbackNode::new(JoinOp::<R>::execute),
+            state: self.0,
+            recv: UnsafeCell::new(Some(receiver)),
+            _marker: PhantomPinned,
+        })
+    }
+}
@@ -0,0 +1,274 @@
+use core::{
+    convert::Infallible,
+    marker::{PhantomData, PhantomPinned},
+    pin::Pin,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering::*},
+};
+
+use placid::prelude::*;
+
+use crate::{
+    OperationState, Receiver, Sender, SenderTo,
+    basic::OnceSlot,
+    completion::No,
+    env::{EmptyEnv, GetEnv, StopEnv},
+    stop::{self, InPlaceStopSource},
+    util::{
+        ONESHOT_COMPLETED,
+        detached::{HeapOp, start_in_heap},
+    },
+};
+
+/// The part of [`ScopedOp`] that the spawned operations refer to.
+struct ScopeCore {
+    source: InPlaceStopSource,
+    // The spawned operations, plus one for the scope function.
+    outstanding: AtomicUsize,
+    // Whether the last one to leave is done with the operation state.
+    released: AtomicBool,
+    finish: unsafe fn(NonNull<ScopeCore>),
+}
+
+impl ScopeCore {
+    fn enter(&self) {
+        self.outstanding.fetch_add(1, Relaxed);
+    }
+
+    /// # Safety
+    ///
+    /// `this` must point to the core of a running [`ScopedOp`], and must not be
+    /// used afterwards.
+    unsafe fn leave(this: NonNull<Self>) {
+        // SAFETY: The operation state is not released until the last one leaves.
+        unsafe {
+            if this.as_ref().outstanding.fetch_sub(1, AcqRel) == 1 {
+                (this.as_ref().finish)(this);
+            }
+        }
+    }
+}
+
+/// # Safety
+///
+/// `core` must point to the core of a [`ScopedOp<F, T, R>`], derived from a
+/// pointer to the whole operation state.
+unsafe fn finish<F, T, R: Receiver<T>>(core: NonNull<ScopeCore>) {
+    // SAFETY: `core` is the first field of the `repr(C)` operation state.
+    let op = unsafe { core.cast::<ScopedOp<F, T, R>>().as_ref() };
+    let recv = op.recv.take();
+    let value = op.value.take();
+    // The operation state may be released as soon as this is set.
+    op.core.released.store(true, Release);
+    if let (Some(recv), Some(value)) = (recv, value) {
+        recv.set(value);
+    }
+}
+
+/// Counts the spawned operation, or the scope function, as finished on drop.
+struct Leave(NonNull<ScopeCore>);
+
+impl Drop for Leave {
+    fn drop(&mut self) {
+        // SAFETY: Each spawned operation leaves only once.
+        unsafe { ScopeCore::leave(self.0) };
+    }
+}
+
+/// The receiver of an operation spawned into a [`Scope`].
+pub struct ScopedRecv {
+    // `_op` must come first, so that the operation state is freed before it is
+    // counted as finished.
+    _op: HeapOp,
+    scope: Leave,
+}
+
+// SAFETY: The core is synchronized.
+unsafe impl Send for ScopedRecv {}
+// SAFETY: Same as above.
+unsafe impl Sync for ScopedRecv {}
+
+impl GetEnv for ScopedRecv {
+    type Env = StopEnv<EmptyEnv>;
+
+    fn get_env(&self) -> StopEnv<EmptyEnv> {
+        // SAFETY: The stop source lives in the scope, which outlives the spawned
+        // operation.
+        unsafe {
+            let source = &raw mut (*self.scope.0.as_ptr()).source;
+            StopEnv::new(EmptyEnv, NonNull::new_unchecked(source))
+        }
+    }
+}
+
+impl<T> Receiver<T, Infallible> for ScopedRecv {
+    fn set(self, _: T) {}
+
+    fn set_error(self, error: Infallible) {
+        match error {}
+    }
+
+    fn set_stopped(self) {}
+}
+
+/// A scope to spawn operations that may borrow from `'env`, passed to the
+/// function of [`scope`].
+pub struct Scope<'s, 'env: 's> {
+    core: NonNull<ScopeCore>,
+    // Invariant over both lifetimes, as `std::thread::Scope` is.
+    _marker: PhantomData<(&'s mut &'s (), &'env mut &'env ())>,
+}
+
+// SAFETY: The core is synchronized.
+unsafe impl Send for Scope<'_, '_> {}
+// SAFETY: Same as above.
+unsafe impl Sync for Scope<'_, '_> {}
+
+impl<'env> Scope<'_, 'env> {
+    fn core(&self) -> &ScopeCore {
+        // SAFETY: The scope does not outlive the call of the scope function, which
+        // holds the operation state from completing.
+        unsafe { self.core.as_ref() }
+    }
+
+    /// Connects the sender and starts it in a heap-allocated operation state,
+    /// which is freed once the operation completes.
+    ///
+    /// The values are discarded, and errors must have been handled beforehand.
+    pub fn spawn<S>(&self, sender: S) -> Result<(), S::ConnectError>
+    where
+        S: SenderTo<ScopedRecv, Error = Infallible, Operation: Send + 'env>,
+    {
+        self.core().enter();
+        // SAFETY: The scope does not complete, and its operation state is not
+        // dropped, until all the spawned operations have completed. Since the
+        // operation state of the scope may only borrow from `'env` as well, the
+        // spawned operations do not outlive anything they borrow.
+        unsafe { start_in_heap(sender, |op| ScopedRecv { _op: op, scope: Leave(self.core) }) }
+    }
+
+    /// Requests all the outstanding and future operations in the scope to stop.
+    pub fn request_stop(&self) -> bool {
+        self.core().source.request_stop()
+    }
+}
+
+/// A sender that runs its function with a [`Scope`], and completes with its
+/// result after all the operations spawned into the scope have completed.
+pub struct Scoped<'env, F, T> {
+    f: F,
+    _marker: PhantomData<(&'env mut &'env (), fn() -> T)>,
+}
+
+impl<F, T> GetEnv for Scoped<'_, F, T> {
+    type Env = EmptyEnv;
+
+    fn get_env(&self) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<'env, F, T> Sender for Scoped<'env, F, T>
+where
+    F: for<'s> FnOnce(&'s Scope<'s, 'env>) -> T,
+{
+    type Output = T;
+    type Error = Infallible;
+    type Stopped = No;
+}
+
+/// The operation state of [`Scoped`].
+#[repr(C)]
+pub struct ScopedOp<F, T, R> {
+    // `core` must come first, since it is cast back to the whole operation state
+    // on finishing.
+    core: ScopeCore,
+    f: Option<F>,
+    value: OnceSlot<T>,
+    recv: OnceSlot<R>,
+    _marker: PhantomPinned,
+}
+
+impl<F, T, R> Drop for ScopedOp<F, T, R> {
+    fn drop(&mut self) {
+        if self.f.is_some() {
+            return;
+        }
+        // The spawned operations may borrow from the environment of the scope,
+        // so the operation state must wait for them even if the result is no
+        // longer wanted.
+        drop(self.recv.take());
+        self.core.source.request_stop();
+        while !self.core.released.load(Acquire) {
+            stop::relax();
+        }
+    }
+}
+
+unsafe impl<'env, F, T, R> OperationState for ScopedOp<F, T, R>
+where
+    F: for<'s> FnOnce(&'s Scope<'s, 'env>) -> T,
+    // The last spawned operation to complete delivers the result on its own
+    // thread.
+    T: Send,
+    R: Receiver<T> + Send,
+{
+    unsafe fn start_by_ref(self: Pin<&mut Self>) {
+        // SAFETY: Nothing is moved out of the pinned operation state.
+        let this = unsafe { self.get_unchecked_mut() };
+        let f = this.f.take().expect(ONESHOT_COMPLETED);
+        this.core.outstanding.store(1, Relaxed);
+
+        // The pointer to the core is derived from the whole operation state, so
+        // that it can be cast back on finishing.
+        let core = NonNull::from_mut(this).cast::<ScopeCore>();
+        let scope = Scope { core, _marker: PhantomData };
+        // The scope function leaves even if it panics, in which case the receiver
+        // is dropped without completion after the spawned operations.
+        let leave = Leave(core);
+        let value = f(&scope);
+
+        // SAFETY: The operation state is pinned and does not complete until the
+        // scope function leaves below.
+        let _ = unsafe { (*core.cast::<Self>().as_ptr()).value.set(value) };
+        drop(leave);
+    }
+}
+
+impl<'env, F, T, R> SenderTo<R> for Scoped<'env, F, T>
+where
+    F: for<'s> FnOnce(&'s Scope<'s, 'env>) -> T,
+    T: Send,
+    R: Receiver<T> + Send,
+{
+    type Operation = ScopedOp<F, T, R>;
+    type ConnectError = Infallible;
+
+    fn connect(self, receiver: R) -> impl InitPin<Self::Operation, Error = Self::ConnectError> {
+        init::with(move || ScopedOp {
+            core: ScopeCore {
+                source: InPlaceStopSource::new(),
+                outstanding: AtomicUsize::new(0),
+                released: AtomicBool::new(false),
+                finish: finish::<F, T, R>,
+            },
+            f: Some(self.f),
+            value: OnceSlot::new(),
+            recv: OnceSlot::full(receiver),
+            _marker: PhantomPinned,
+        })
+    }
+}
+
+/// Creates a sender that runs `f` with a [`Scope`], in which it may spawn
+/// operations borrowing from `'env`, in the spirit of `std::thread::scope`.
+///
+/// The sender completes with the result of `f` after all the spawned
+/// operations have completed. If its operation state is dropped before then,
+/// the spawned operations are requested to stop, and the drop waits for them.
+pub fn scope<'env, F, T>(f: F) -> Scoped<'env, F, T>
+where
+    F: for<'s> FnOnce(&'s Scope<'s, 'env>) -> T,
+{
+    Scoped { f, _marker: PhantomData }
+}
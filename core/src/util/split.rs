@@ -0,0 +1,223 @@
+use alloc::sync::Arc;
+use core::{
+    cell::UnsafeCell,
+    convert::Infallible,
+    mem::{self, ManuallyDrop},
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering::*},
+};
+
+use placid::{
+    pin::{DropSlot, DroppingSlot},
+    prelude::*,
+};
+use spin::Once;
+
+use crate::{
+    OperationState, Receiver, Sender, SenderTo,
+    completion::Completion,
+    env::{EmptyEnv, GetEnv},
+    stop::Callbacks,
+    util::waiter::{Waited, WaiterOp},
+};
+
+struct SplitCore<T, E> {
+    // Set once with the list of waiters locked, and never changed afterwards.
+    completion: Once<Completion<T, E>>,
+    // The consumers that started before the completion, linked in place in
+    // their operation states.
+    waiters: Callbacks,
+}
+
+struct SplitShared<O, T, E> {
+    op: UnsafeCell<O>,
+    started: AtomicBool,
+    core: Arc<SplitCore<T, E>>,
+}
+
+// SAFETY: `op` is only accessed by the consumer that starts it.
+unsafe impl<O: Send, T: Send + Sync, E: Send + Sync> Send for SplitShared<O, T, E> {}
+// SAFETY: Same as above, and the core is synchronized.
+unsafe impl<O: Send, T: Send + Sync, E: Send + Sync> Sync for SplitShared<O, T, E> {}
+
+impl<O: OperationState, T: Clone, E: Clone> Waited for SplitShared<O, T, E> {
+    type Output = T;
+    type Error = E;
+
+    fn waiters(&self) -> &Callbacks {
+        &self.core.waiters
+    }
+
+    fn completion(&self) -> Option<Completion<T, E>> {
+        self.core.completion.get().cloned()
+    }
+
+    fn waiting(&self) {
+        if self.started.swap(true, AcqRel) {
+            return;
+        }
+        // SAFETY: The operation lives in place in the `Arc` until the last consumer
+        // is dropped, and it is started only once here.
+        unsafe { Pin::new_unchecked(&mut *self.op.get()).start_by_ref() };
+    }
+}
+
+/// The receiver of the shared operation of [`Split`].
+pub struct SplitRecv<T, E> {
+    // Owned apart from the shared state, which the last consumer may drop while
+    // the others are being completed.
+    core: Arc<SplitCore<T, E>>,
+}
+
+impl<T, E> SplitRecv<T, E> {
+    fn finish(self, completion: Completion<T, E>) {
+        let core = self.core;
+        let waiters = core.waiters.lock();
+        core.completion.call_once(|| completion);
+        drop(waiters);
+        // The consumers are completed outside of the lock, since they may connect
+        // and start other consumers of the same sender.
+        core.waiters.execute_all();
+    }
+}
+
+impl<T, E> GetEnv for SplitRecv<T, E> {
+    type Env = EmptyEnv;
+
+    fn get_env(&self) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<T, E> Receiver<T, E> for SplitRecv<T, E> {
+    fn set(self, value: T) {
+        self.finish(Completion::Value(value));
+    }
+
+    fn set_error(self, error: E) {
+        self.finish(Completion::Error(error));
+    }
+
+    fn set_stopped(self) {
+        self.finish(Completion::Stopped);
+    }
+}
+
+type Shared<S> = SplitShared<
+    <S as SenderTo<SplitRecv<SplitOutput<S>, SplitError<S>>>>::Operation,
+    SplitOutput<S>,
+    SplitError<S>,
+>;
+type SplitOutput<S> = <S as Sender>::Output;
+type SplitError<S> = <S as Sender>::Error;
+
+/// A sender that runs the underlying sender at most once, and completes all
+/// of its consumers with clones of the result.
+pub struct Split<S>(Arc<Shared<S>>)
+where
+    S: SenderTo<SplitRecv<S::Output, S::Error>>;
+
+impl<S> Clone for Split<S>
+where
+    S: SenderTo<SplitRecv<S::Output, S::Error>>,
+{
+    fn clone(&self) -> Self {
+        Split(self.0.clone())
+    }
+}
+
+impl<S> GetEnv for Split<S>
+where
+    S: SenderTo<SplitRecv<S::Output, S::Error>>,
+{
+    type Env = EmptyEnv;
+
+    fn get_env(&self) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<S> Sender for Split<S>
+where
+    S: SenderTo<SplitRecv<S::Output, S::Error>>,
+{
+    type Output = S::Output;
+    type Error = S::Error;
+    type Stopped = S::Stopped;
+}
+
+/// The operation state of a consumer of [`Split`].
+pub struct SplitOp<S, R>(WaiterOp<Shared<S>, R>)
+where
+    S: SenderTo<SplitRecv<S::Output, S::Error>>,
+    S::Output: Clone,
+    S::Error: Clone;
+
+unsafe impl<S, R> OperationState for SplitOp<S, R>
+where
+    S: SenderTo<SplitRecv<S::Output, S::Error>>,
+    S::Output: Clone,
+    S::Error: Clone,
+    R: Receiver<S::Output, S::Error>,
+{
+    unsafe fn start_by_ref(self: Pin<&mut Self>) {
+        // SAFETY: The consumer is pinned along with the operation, and started only
+        // once here.
+        unsafe { self.map_unchecked_mut(|op| &mut op.0).start() }
+    }
+}
+
+impl<S, R> SenderTo<R> for Split<S>
+where
+    S: SenderTo<SplitRecv<S::Output, S::Error>>,
+    S::Output: Clone,
+    S::Error: Clone,
+    R: Receiver<S::Output, S::Error>,
+{
+    type Operation = SplitOp<S, R>;
+    type ConnectError = Infallible;
+
+    fn connect(self, receiver: R) -> impl InitPin<Self::Operation, Error = Self::ConnectError> {
+        init::with(move || SplitOp(WaiterOp::new(self.0, receiver)))
+    }
+}
+
+/// Shares the sender among any number of consumers, each of which completes
+/// with a clone of its result.
+///
+/// The sender is connected in place in a shared allocation right away, and
+/// started once the first consumer starts. Consumers that start after it has
+/// completed get the result immediately.
+///
+/// Without the `std` feature, a consumer that starts before the completion
+/// must not be dropped from within its own receiver, for the same reason as
+/// [`InPlaceStopCallback`](crate::stop::InPlaceStopCallback).
+pub fn split<S>(sender: S) -> Result<Split<S>, S::ConnectError>
+where
+    S: SenderTo<SplitRecv<S::Output, S::Error>>,
+{
+    let mut shared = Arc::<Shared<S>>::new_uninit();
+    let ptr = Arc::get_mut(&mut shared).unwrap().as_mut_ptr();
+    let core = Arc::new(SplitCore {
+        completion: Once::new(),
+        waiters: Callbacks::new(),
+    });
+    let recv = SplitRecv { core: core.clone() };
+    // SAFETY: The fields are initialized in place before the `Arc` is shared.
+    unsafe {
+        (&raw mut (*ptr).core).write(core);
+        (&raw mut (*ptr).started).write(AtomicBool::new(false));
+
+        let mut subslot = ManuallyDrop::new(DroppingSlot::new());
+        let subslot_ref = DropSlot::new_unchecked(&mut subslot);
+        let op = UnsafeCell::raw_get(&raw mut (*ptr).op);
+        match Uninit::from_raw(op).try_write_pin(sender.connect(recv), subslot_ref) {
+            Ok(p) => mem::forget(p),
+            Err(err) => {
+                (&raw mut (*ptr).core).drop_in_place();
+                return Err(err.error);
+            }
+        }
+        Ok(Split(shared.assume_init()))
+    }
+}
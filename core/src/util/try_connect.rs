@@ -0,0 +1,170 @@
+use core::{convert::Infallible, marker::PhantomPinned, pin::Pin, ptr::NonNull};
+
+use pin_project::pin_project;
+use placid::{place::DynPlace, prelude::*};
+
+use crate::{
+    OperationState, Receiver, Sender, SenderTo,
+    env::{EnvOf, GetEnv},
+    util::ONESHOT_COMPLETED,
+};
+
+/// The receiver of the inner operation of [`TryConnect`].
+pub struct TryConnectRecv<R> {
+    // Effectively a `&'op mut Option<R>`.
+    recv: NonNull<Option<R>>,
+}
+
+// SAFETY: The receiver is the only one accessing `recv` after connecting.
+unsafe impl<R: Send> Send for TryConnectRecv<R> {}
+// SAFETY: Same as above.
+unsafe impl<R: Sync> Sync for TryConnectRecv<R> {}
+
+impl<R> TryConnectRecv<R> {
+    /// Creates a receiver that completes the receiver in `recv`, and leaves it
+    /// in place if dropped without completion.
+    ///
+    /// # Safety
+    ///
+    /// `recv` must hold the receiver, and be accessed only through the created
+    /// receiver until it is completed or dropped, and outlive it.
+    pub unsafe fn new(recv: NonNull<Option<R>>) -> Self {
+        TryConnectRecv { recv }
+    }
+
+    fn take(self) -> R {
+        // SAFETY: `recv` lives after the inner operation, and thus this receiver,
+        // in the operation state.
+        unsafe { (*self.recv.as_ptr()).take() }.expect(ONESHOT_COMPLETED)
+    }
+}
+
+impl<R: GetEnv> GetEnv for TryConnectRecv<R> {
+    type Env = EnvOf<R>;
+
+    fn get_env(&self) -> EnvOf<R> {
+        // SAFETY: See `TryConnectRecv::take`.
+        let recv = unsafe { self.recv.as_ref() };
+        recv.as_ref().expect(ONESHOT_COMPLETED).get_env()
+    }
+}
+
+impl<T, E, R: Receiver<T, E>> Receiver<T, E> for TryConnectRecv<R> {
+    fn set(self, value: T) {
+        self.take().set(value);
+    }
+
+    fn set_error(self, error: E) {
+        self.take().set_error(error);
+    }
+
+    fn set_stopped(self) {
+        self.take().set_stopped();
+    }
+}
+
+/// A sender that connects the inner sender on starting, and completes with
+/// the connect error as an error if the connection fails.
+pub struct TryConnect<S>(S);
+
+impl<S: Sender> GetEnv for TryConnect<S> {
+    type Env = EnvOf<S>;
+
+    fn get_env(&self) -> EnvOf<S> {
+        self.0.get_env()
+    }
+}
+
+impl<S: Sender> Sender for TryConnect<S> {
+    type Output = S::Output;
+    type Error = S::Error;
+    type Stopped = S::Stopped;
+}
+
+#[derive(InitPin)]
+#[pin_project]
+pub struct TryConnectOp<S, O, R> {
+    #[pin]
+    pinned: PhantomPinned,
+    sender: Option<S>,
+    // `next_op` must come before `recv`, since its receiver refers to it.
+    #[pin]
+    next_op: DynPlace<O>,
+    recv: Option<R>,
+}
+
+unsafe impl<S, R> OperationState for TryConnectOp<S, S::Operation, R>
+where
+    S: SenderTo<TryConnectRecv<R>, Operation: Sized, ConnectError: Into<S::Error>>,
+    R: Receiver<S::Output, S::Error>,
+{
+    unsafe fn start_by_ref(self: Pin<&mut Self>) {
+        let this = self.project();
+        let sender = this.sender.take().expect(ONESHOT_COMPLETED);
+        // SAFETY: `recv` lives after the inner operation in the operation state,
+        // which is started only once here and not forgotten after started.
+        unsafe { try_start_in_place(sender, this.next_op, NonNull::from_mut(this.recv)) }
+    }
+}
+
+/// Connects the sender to the receiver in `recv`, and starts the operation in
+/// `place`, or completes the receiver with the connect error as an error if
+/// the connection fails.
+///
+/// This is what [`TryConnect`] does on starting, for operation states that
+/// connect their continuations in place, e.g. that of
+/// [`and_then`](super::and_then).
+///
+/// # Safety
+///
+/// - `recv` must hold the receiver, and be accessed only through the operation
+///   until it is completed, and outlive it.
+/// - The operation must be started only once, and not be forgotten after
+///   started, as in [`OperationState::start_by_ref`].
+pub unsafe fn try_start_in_place<S, R>(
+    sender: S,
+    place: Pin<&mut DynPlace<S::Operation>>,
+    recv: NonNull<Option<R>>,
+) where
+    S: SenderTo<TryConnectRecv<R>, Operation: Sized, ConnectError: Into<S::Error>>,
+    R: Receiver<S::Output, S::Error>,
+{
+    // SAFETY: Guaranteed by the caller.
+    let next_op = sender.connect(unsafe { TryConnectRecv::new(recv) });
+
+    match place.try_insert_pin(next_op) {
+        // SAFETY: Guaranteed by the caller.
+        Ok(next_op) => unsafe { next_op.start_by_ref() },
+        Err(err) => {
+            // SAFETY: The receiver of the failed connection has been dropped, and
+            // left the outer receiver in place.
+            let recv = unsafe { (*recv.as_ptr()).take() };
+            recv.expect(ONESHOT_COMPLETED).set_error(err.error.into());
+        }
+    }
+}
+
+impl<S, R> SenderTo<R> for TryConnect<S>
+where
+    S: SenderTo<TryConnectRecv<R>, Operation: Sized, ConnectError: Into<S::Error>>,
+    R: Receiver<S::Output, S::Error>,
+{
+    type Operation = TryConnectOp<S, S::Operation, R>;
+    type ConnectError = Infallible;
+
+    fn connect(self, receiver: R) -> impl InitPin<Self::Operation, Error = Self::ConnectError> {
+        init_pin!(TryConnectOp {
+            pinned: PhantomPinned,
+            sender: || Some(self.0),
+            next_op: DynPlace::new,
+            recv: || Some(receiver),
+        })
+    }
+}
+
+/// Defers connecting the sender until the operation starts, and turns the
+/// connect error into an error completion, so that connecting itself cannot
+/// fail.
+pub const fn try_connect<S: Sender>(sender: S) -> TryConnect<S> {
+    TryConnect(sender)
+}
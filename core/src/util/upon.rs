@@ -0,0 +1,137 @@
+use core::{convert::Infallible, marker::PhantomData, pin::Pin};
+
+use placid::prelude::*;
+use tsum::{Sum, T, t};
+
+use crate::{
+    Receiver, Sender,
+    basic::*,
+    completion::No,
+    env::{EnvOf, GetEnv},
+    util::ONESHOT_COMPLETED,
+};
+
+pub struct UponState<T>(Option<T>);
+
+impl<T> Unpin for UponState<T> {}
+
+pub struct UponErrorExpr<S, F>(PhantomData<(S, F)>);
+
+impl<S, F> SenderExpr for UponErrorExpr<S, F>
+where
+    S: Sender,
+    F: FnOnce(S::Error) -> S::Output,
+{
+    type Output = S::Output;
+    type Error = Infallible;
+    type Stopped = S::Stopped;
+    type Attrs = EnvOf<S>;
+    type Data = F;
+    type SubSenders = T![S];
+
+    fn attrs(_: &F, sub_senders: &T![S]) -> EnvOf<S> {
+        sub_senders.0.get_env()
+    }
+}
+
+impl<S, F, R> SenderExprTo<R> for UponErrorExpr<S, F>
+where
+    S: Sender,
+    F: FnOnce(S::Error) -> S::Output,
+    R: Receiver<S::Output>,
+{
+    type State = UponState<(F, R)>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(data: Self::Data, _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
+        init::with(move || UponState(Some((data, recv))))
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let (_, recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set(value.into_inner());
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let (func, recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set(func(error.into_inner()));
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        if let Some((_, recv)) = state.state_mut().0.take() {
+            recv.set_stopped();
+        }
+    }
+}
+
+pub type UponError<S, F> = BasicSender<UponErrorExpr<S, F>>;
+
+/// Maps the error of the sender into a value.
+pub const fn upon_error<S, F>(sender: S, func: F) -> UponError<S, F>
+where
+    S: Sender,
+    F: FnOnce(S::Error) -> S::Output,
+{
+    BasicSender::new(func, t![sender])
+}
+
+pub struct UponStoppedExpr<S, F>(PhantomData<(S, F)>);
+
+impl<S, F> SenderExpr for UponStoppedExpr<S, F>
+where
+    S: Sender,
+    F: FnOnce() -> S::Output,
+{
+    type Output = S::Output;
+    type Error = S::Error;
+    type Stopped = No;
+    type Attrs = EnvOf<S>;
+    type Data = F;
+    type SubSenders = T![S];
+
+    fn attrs(_: &F, sub_senders: &T![S]) -> EnvOf<S> {
+        sub_senders.0.get_env()
+    }
+}
+
+impl<S, F, R> SenderExprTo<R> for UponStoppedExpr<S, F>
+where
+    S: Sender,
+    F: FnOnce() -> S::Output,
+    R: Receiver<S::Output, S::Error>,
+{
+    type State = UponState<(F, R)>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(data: Self::Data, _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
+        init::with(move || UponState(Some((data, recv))))
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        let (_, recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set(value.into_inner());
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        let (_, recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set_error(error.into_inner());
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        let (func, recv) = state.state_mut().0.take().expect(ONESHOT_COMPLETED);
+        recv.set(func());
+    }
+}
+
+pub type UponStopped<S, F> = BasicSender<UponStoppedExpr<S, F>>;
+
+/// Maps the stopped signal of the sender into a value.
+pub const fn upon_stopped<S, F>(sender: S, func: F) -> UponStopped<S, F>
+where
+    S: Sender,
+    F: FnOnce() -> S::Output,
+{
+    BasicSender::new(func, t![sender])
+}
@@ -2,14 +2,21 @@ use core::{convert::Infallible, marker::PhantomData, pin::Pin};
 
 use placid::prelude::*;
 
-use crate::{basic::*, traits::Receiver, util::ONESHOT_COMPLETED};
+use crate::{basic::*, completion::No, env::EmptyEnv, traits::Receiver, util::ONESHOT_COMPLETED};
 
 pub struct ValueExpr<T>(PhantomData<T>);
 
 impl<T> SenderExpr for ValueExpr<T> {
     type Output = T;
+    type Error = Infallible;
+    type Stopped = No;
+    type Attrs = EmptyEnv;
     type Data = T;
     type SubSenders = ();
+
+    fn attrs(_: &T, _: &()) -> EmptyEnv {
+        EmptyEnv
+    }
 }
 
 pub struct ValueState<T>(Option<T>);
@@ -18,8 +25,8 @@ impl<T> Unpin for ValueState<T> {}
 
 impl<R: Receiver<T>, T> SenderExprTo<R> for ValueExpr<T> {
     type State = ValueState<(T, R)>;
-    type Error = Infallible;
-    type CreateState = impl InitPin<Self::State, Error = Self::Error>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
 
     fn create_state(data: Self::Data, _: &mut (), recv: R) -> Self::CreateState {
         init::value(ValueState(Some((data, recv))))
@@ -36,6 +43,10 @@ impl<R: Receiver<T>, T> SenderExprTo<R> for ValueExpr<T> {
     fn complete(_: Pin<&mut State<Self, R>>, value: tsum::Sum<()>) {
         value.unreachable();
     }
+
+    fn error(_: Pin<&mut State<Self, R>>, error: tsum::Sum<()>) {
+        error.unreachable();
+    }
 }
 
 pub type Value<T> = BasicSender<ValueExpr<T>>;
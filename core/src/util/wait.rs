@@ -1,17 +1,44 @@
 use placid::prelude::*;
 
-use crate::{OperationState, Receiver, SenderTo};
+#[cfg(feature = "std")]
+use super::catch_unwind::CatchError;
+use crate::{
+    OperationState, Receiver, SenderTo,
+    env::{EmptyEnv, GetEnv},
+};
 
 #[derive(Debug, thiserror::Error)]
 #[error("the sender operation was cancelled")]
 pub struct CanceledError;
 
-pub struct WaitRecv<T>(oneshot::Sender<T>);
+#[derive(Debug, thiserror::Error)]
+pub enum WaitError<E> {
+    #[error("the sender operation completed with an error")]
+    Error(E),
+    #[error(transparent)]
+    Canceled(#[from] CanceledError),
+}
+
+pub struct WaitRecv<T, E>(oneshot::Sender<Result<T, E>>);
+
+impl<T, E> GetEnv for WaitRecv<T, E> {
+    type Env = EmptyEnv;
+
+    fn get_env(&self) -> EmptyEnv {
+        EmptyEnv
+    }
+}
 
-impl<T> Receiver<T> for WaitRecv<T> {
+impl<T, E> Receiver<T, E> for WaitRecv<T, E> {
     fn set(self, value: T) {
-        let _ = self.0.send(value);
+        let _ = self.0.send(Ok(value));
     }
+
+    fn set_error(self, error: E) {
+        let _ = self.0.send(Err(error));
+    }
+
+    fn set_stopped(self) {}
 }
 
 /// Waits for the sender to complete and returns the output value.
@@ -20,27 +47,46 @@ impl<T> Receiver<T> for WaitRecv<T> {
 ///
 /// The caller must ensure that the caller future is properly dropped, i.e., not
 /// `mem::forget`ed.
-pub async unsafe fn wait<T, S>(sender: S) -> Result<T, CanceledError>
+pub async unsafe fn wait<T, E, S>(sender: S) -> Result<T, WaitError<E>>
 where
-    S: SenderTo<WaitRecv<T>, Output = T>,
+    S: SenderTo<WaitRecv<T, E>, Output = T, Error = E>,
 {
     let (s, r) = oneshot::channel();
 
     let op = pown!(sender.connect(WaitRecv(s)));
     OperationState::start(op);
 
-    r.await.map_err(|_| CanceledError)
+    r.await
+        .map_err(|_| CanceledError)?
+        .map_err(WaitError::Error)
 }
 
 #[cfg(feature = "std")]
-pub fn sync_wait<T, S>(sender: S) -> Result<T, CanceledError>
+pub fn sync_wait<T, E, S>(sender: S) -> Result<T, WaitError<E>>
 where
-    S: SenderTo<WaitRecv<T>, Output = T>,
+    S: SenderTo<WaitRecv<T, E>, Output = T, Error = E>,
 {
     let (s, r) = oneshot::channel();
 
     let op = pown!(sender.connect(WaitRecv(s)));
     OperationState::start(op);
 
-    r.recv().map_err(|_| CanceledError)
+    r.recv()
+        .map_err(|_| CanceledError)?
+        .map_err(WaitError::Error)
+}
+
+/// Waits like [`sync_wait`] for the sender wrapped by
+/// [`catch_unwind`](super::catch_unwind), resuming the caught panic on the
+/// waiting thread.
+#[cfg(feature = "std")]
+pub fn sync_wait_unwind<T, E, S>(sender: S) -> Result<T, WaitError<E>>
+where
+    S: SenderTo<WaitRecv<T, CatchError<E>>, Output = T, Error = CatchError<E>>,
+{
+    sync_wait(sender).map_err(|err| match err {
+        WaitError::Error(CatchError::Error(error)) => WaitError::Error(error),
+        WaitError::Error(CatchError::Panic(payload)) => std::panic::resume_unwind(payload),
+        WaitError::Canceled(err) => WaitError::Canceled(err),
+    })
 }
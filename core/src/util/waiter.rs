@@ -0,0 +1,122 @@
+use alloc::sync::Arc;
+use core::{cell::UnsafeCell, marker::PhantomPinned, pin::Pin, ptr::NonNull};
+
+use crate::{
+    Receiver,
+    completion::Completion,
+    stop::{CallbackNode, Callbacks},
+    util::ONESHOT_COMPLETED,
+};
+
+/// The state shared between an operation and the consumers waiting for its
+/// completion.
+pub(crate) trait Waited {
+    type Output;
+    type Error;
+
+    /// The consumers that started before the completion, linked in place in
+    /// their operation states.
+    fn waiters(&self) -> &Callbacks;
+
+    /// Returns the completion for a consumer, if the operation has completed.
+    ///
+    /// The completion must be set with the waiters locked, before they are
+    /// executed.
+    fn completion(&self) -> Option<Completion<Self::Output, Self::Error>>;
+
+    /// Called once a consumer starts waiting for the completion.
+    fn waiting(&self) {}
+
+    /// Called once a consumer is dropped before it is completed.
+    fn abandoned(&self) {}
+}
+
+/// The operation state of a consumer of a shared operation, which is linked
+/// as a node in the waiters of the shared state until it completes.
+#[repr(C)]
+pub(crate) struct WaiterOp<W: Waited, R> {
+    // `node` must be the first field so that it can be cast back to `Self`.
+    node: CallbackNode,
+    shared: Arc<W>,
+    // Taken by whoever completes it, either on starting or once the node is
+    // unlinked for execution.
+    recv: UnsafeCell<Option<R>>,
+    _marker: PhantomPinned,
+}
+
+// SAFETY: The receiver is only accessed by the consumer, or by the execution of
+// the node, which the consumer waits for on dropping.
+unsafe impl<W: Waited, R: Send> Send for WaiterOp<W, R> where Arc<W>: Send {}
+// SAFETY: Same as above.
+unsafe impl<W: Waited, R: Send> Sync for WaiterOp<W, R> where Arc<W>: Sync {}
+
+impl<W, R> WaiterOp<W, R>
+where
+    W: Waited,
+    R: Receiver<W::Output, W::Error>,
+{
+    pub(crate) fn new(shared: Arc<W>, recv: R) -> Self {
+        WaiterOp {
+            node: CallbackNode::new(Self::execute),
+            shared,
+            recv: UnsafeCell::new(Some(recv)),
+            _marker: PhantomPinned,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `node` must point to the `node` field of a valid `Self`, and the shared
+    /// operation must have completed.
+    unsafe fn execute(node: NonNull<CallbackNode>) {
+        let this = node.cast::<Self>().as_ptr();
+        // SAFETY: The receiver is taken out only once, and the completion is set
+        // before the waiters are executed.
+        let (recv, completion) =
+            unsafe { ((*(*this).recv.get()).take(), (*this).shared.completion()) };
+        completion
+            .expect(ONESHOT_COMPLETED)
+            .deliver(recv.expect(ONESHOT_COMPLETED));
+    }
+
+    /// Completes the receiver if the shared operation has completed, or links
+    /// the node to wait for it otherwise.
+    ///
+    /// # Safety
+    ///
+    /// This function must be called at most once.
+    pub(crate) unsafe fn start(self: Pin<&mut Self>) {
+        let this = self.into_ref().get_ref();
+        let waiters = this.shared.waiters();
+        let mut list = waiters.lock();
+        match this.shared.completion() {
+            None => {
+                // SAFETY: The operation is pinned, and it deregisters the node on
+                // dropping.
+                unsafe { list.push_back(NonNull::from_ref(&this.node)) };
+                drop(list);
+                this.shared.waiting();
+            }
+            Some(completion) => {
+                drop(list);
+                // SAFETY: The node is not linked, so we are the only one accessing the
+                // receiver.
+                let recv = unsafe { (*this.recv.get()).take() };
+                completion.deliver(recv.expect(ONESHOT_COMPLETED));
+            }
+        }
+    }
+}
+
+impl<W: Waited, R> Drop for WaiterOp<W, R> {
+    fn drop(&mut self) {
+        // SAFETY: The node is only ever linked in the list of the shared state.
+        unsafe { self.shared.waiters().deregister(&self.node) };
+        // The receiver is dropped without completion along with the operation if
+        // it is still waiting.
+        if let Some(recv) = self.recv.get_mut().take() {
+            self.shared.abandoned();
+            drop(recv);
+        }
+    }
+}
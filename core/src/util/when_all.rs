@@ -0,0 +1,473 @@
+use core::{
+    convert::Infallible,
+    marker::PhantomData,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering::*},
+};
+
+use placid::prelude::*;
+use tsum::Sum;
+use tuple_list::{Tuple, TupleList};
+
+use crate::{
+    Receiver,
+    basic::*,
+    completion::Completion,
+    env::{EmptyEnv, EnvOf, QueryStopToken, StopEnv},
+    list::{
+        OperationStateList, ResultList, SenderErrorList, SenderList, SenderOutputList,
+        SenderStoppedList,
+    },
+};
+
+/// Completes with the values of all the sub-senders, or with the first error
+/// or stopped signal after all of them complete.
+pub struct WhenAllExpr<L>(PhantomData<L>);
+
+impl<L> SenderExpr for WhenAllExpr<L>
+where
+    L: SenderList<OutputList: TupleList> + ListPlace,
+{
+    type Output = <SenderOutputList<L> as TupleList>::Tuple;
+    type Error = Sum<SenderErrorList<L>>;
+    type Stopped = SenderStoppedList<L>;
+    type Attrs = EmptyEnv;
+    type Data = ();
+    type SubSenders = L;
+
+    fn attrs(_: &(), _: &L) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<L, R> SenderExprTo<R> for WhenAllExpr<L>
+where
+    L: SenderList<OutputList: TupleList + SlotList> + ListPlace,
+    R: Receiver<Self::Output, Self::Error>,
+{
+    type State = FanIn<AllOf<SenderOutputList<L>, Self::Error>, R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(_: (), _: &mut L, recv: R) -> Self::CreateState {
+        let len = <SenderOutputList<L> as SlotList>::LEN;
+        init::with(move || FanIn::new(len, AllOf::new(), recv))
+    }
+
+    fn start(state: StateRef<'_, Self, R>, subops: Pin<&mut ConnectAllOps<Self, R>>)
+    where
+        State<Self, R>: ConnectAll<Self, R>,
+    {
+        // The state may be gone as soon as the sub-operations start, so it is only
+        // touched here if there is none of them.
+        if <SenderOutputList<L> as SlotList>::LEN == 0 {
+            if let Some(handoff) = L::shared(state).state().get_ref().hand_off_empty() {
+                handoff.complete(TupleList::into_tuple);
+            }
+            return;
+        }
+        // SAFETY: Recursion invariant holds.
+        unsafe { subops.start_list_by_ref() };
+    }
+
+    fn complete(state: StateRef<'_, Self, R>, value: Sum<SenderOutputList<L>>) {
+        if let Some(handoff) = L::shared(state).state().get_ref().set(value) {
+            handoff.complete(TupleList::into_tuple);
+        }
+    }
+
+    fn error(state: StateRef<'_, Self, R>, error: Sum<SenderErrorList<L>>) {
+        if let Some(handoff) = L::shared(state).state().get_ref().set_error(error) {
+            handoff.complete(TupleList::into_tuple);
+        }
+    }
+
+    fn stop(state: StateRef<'_, Self, R>) {
+        if let Some(handoff) = L::shared(state).state().get_ref().set_stopped() {
+            handoff.complete(TupleList::into_tuple);
+        }
+    }
+}
+
+pub type WhenAll<S> = BasicSender<WhenAllExpr<<S as Tuple>::TupleList>>;
+
+/// Runs a tuple of senders concurrently, and completes with the tuple of their
+/// values.
+///
+/// All the sub-operations live in place in the operation state, and no heap
+/// allocation is involved.
+pub fn when_all<S>(senders: S) -> WhenAll<S>
+where
+    S: Tuple<TupleList: SenderList<OutputList: TupleList> + ListPlace>,
+{
+    BasicSender::new((), senders.into_tuple_list())
+}
+
+/// Like [`WhenAllExpr`], but requests the other sub-senders to stop as soon as
+/// one of them completes with an error or a stopped signal.
+pub struct WhenAllFailFastExpr<L>(PhantomData<L>);
+
+/// The shared state of [`WhenAllFailFast`].
+pub type FailFastState<L, E, R> = StopFanIn<AllOf<L, E>, R>;
+
+impl<L> SenderExpr for WhenAllFailFastExpr<L>
+where
+    L: SenderList<OutputList: TupleList> + ListPlace,
+{
+    type Output = <SenderOutputList<L> as TupleList>::Tuple;
+    type Error = Sum<SenderErrorList<L>>;
+    type Stopped = SenderStoppedList<L>;
+    type Attrs = EmptyEnv;
+    type Data = ();
+    type SubSenders = L;
+
+    fn attrs(_: &(), _: &L) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<L, R> SenderExprTo<R> for WhenAllFailFastExpr<L>
+where
+    L: SenderList<OutputList: TupleList + SlotList + Send> + ListPlace,
+    Self::Error: Send,
+    R: Receiver<Self::Output, Self::Error, Env: QueryStopToken + Send> + Send,
+{
+    type State = FailFastState<SenderOutputList<L>, Self::Error, R>;
+    type ConnectError = Infallible;
+    type SubEnv = StopEnv<EnvOf<R>>;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(_: (), _: &mut L, recv: R) -> Self::CreateState {
+        let complete = |handoff: Handoff<SenderOutputList<L>, Self::Error, R>| {
+            handoff.complete(TupleList::into_tuple)
+        };
+        let len = <SenderOutputList<L> as SlotList>::LEN;
+        init::with(move || StopFanIn::new(len, AllOf::new(), recv, complete))
+    }
+
+    fn start(state: StateRef<'_, Self, R>, subops: Pin<&mut ConnectAllOps<Self, R>>)
+    where
+        State<Self, R>: ConnectAll<Self, R>,
+    {
+        let state = L::shared(state).state().get_ref();
+        // SAFETY: The state is pinned.
+        unsafe { state.start() };
+        // The state may be gone as soon as the sub-operations start, so it is only
+        // touched here if there is none of them.
+        if <SenderOutputList<L> as SlotList>::LEN == 0 {
+            state.finish_empty();
+            return;
+        }
+        // SAFETY: Recursion invariant holds.
+        unsafe { subops.start_list_by_ref() };
+    }
+
+    fn complete(state: StateRef<'_, Self, R>, value: Sum<SenderOutputList<L>>) {
+        L::shared(state).state().get_ref().set(value);
+    }
+
+    fn error(state: StateRef<'_, Self, R>, error: Sum<SenderErrorList<L>>) {
+        let state = L::shared(state).state().get_ref();
+        // The stop is requested before arriving, after which the state may be gone.
+        state.source().request_stop();
+        state.set_error(error);
+    }
+
+    fn stop(state: StateRef<'_, Self, R>) {
+        let state = L::shared(state).state().get_ref();
+        state.source().request_stop();
+        state.set_stopped();
+    }
+}
+
+pub type WhenAllFailFast<S> = BasicSender<WhenAllFailFastExpr<<S as Tuple>::TupleList>>;
+
+/// Runs a tuple of senders concurrently like [`when_all`], but stops the
+/// others on the first error or stopped signal.
+///
+/// The sub-senders observe the stop token of a stop source in the operation
+/// state, which also forwards the stop requests of the receiver. The operation
+/// still completes after all of them, with the first error if any.
+pub fn when_all_fail_fast<S>(senders: S) -> WhenAllFailFast<S>
+where
+    S: Tuple<TupleList: SenderList<OutputList: TupleList> + ListPlace>,
+{
+    BasicSender::new((), senders.into_tuple_list())
+}
+
+/// Like [`WhenAllExpr`], but completes with the errors of all the failing
+/// sub-senders.
+pub struct WhenAllErrorsExpr<L>(PhantomData<L>);
+
+/// The errors of [`WhenAllErrors`], with `Some` at the index of every failing
+/// sub-sender.
+pub type ErrorsOf<L> = <<SenderErrorList<L> as SlotList>::Stored as TupleList>::Tuple;
+
+/// Gathers the values of all the sub-senders of [`WhenAllErrors`], or the
+/// errors of all the failing ones.
+pub struct AllErrors<L: SlotList, M: SlotList> {
+    values: AllOf<L, Infallible>,
+    errors: M::Slots,
+    failed: AtomicBool,
+}
+
+impl<L, M> Gather for AllErrors<L, M>
+where
+    L: SlotList,
+    M: SlotList<Stored: TupleList>,
+{
+    type Value = L;
+    type Error = <M::Stored as TupleList>::Tuple;
+
+    fn take(&self) -> Completion<L, Self::Error> {
+        if self.failed.load(Acquire) {
+            return Completion::Error(M::take_stored(&self.errors).into_tuple());
+        }
+        match self.values.take() {
+            Completion::Value(values) => Completion::Value(values),
+            Completion::Error(error) => match error {},
+            Completion::Stopped => Completion::Stopped,
+        }
+    }
+}
+
+impl<L> SenderExpr for WhenAllErrorsExpr<L>
+where
+    L: SenderList<OutputList: TupleList, ErrorList: SlotList<Stored: TupleList>> + ListPlace,
+{
+    type Output = <SenderOutputList<L> as TupleList>::Tuple;
+    type Error = ErrorsOf<L>;
+    type Stopped = SenderStoppedList<L>;
+    type Attrs = EmptyEnv;
+    type Data = ();
+    type SubSenders = L;
+
+    fn attrs(_: &(), _: &L) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<L, R> SenderExprTo<R> for WhenAllErrorsExpr<L>
+where
+    L: SenderList<OutputList: TupleList + SlotList, ErrorList: SlotList<Stored: TupleList>>
+        + ListPlace,
+    R: Receiver<Self::Output, Self::Error>,
+{
+    type State = FanIn<AllErrors<SenderOutputList<L>, SenderErrorList<L>>, R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(_: (), _: &mut L, recv: R) -> Self::CreateState {
+        let gather = AllErrors {
+            values: AllOf::new(),
+            errors: <SenderErrorList<L> as SlotList>::empty_slots(),
+            failed: AtomicBool::new(false),
+        };
+        let len = <SenderOutputList<L> as SlotList>::LEN;
+        init::with(move || FanIn::new(len, gather, recv))
+    }
+
+    fn start(state: StateRef<'_, Self, R>, subops: Pin<&mut ConnectAllOps<Self, R>>)
+    where
+        State<Self, R>: ConnectAll<Self, R>,
+    {
+        if <SenderOutputList<L> as SlotList>::LEN == 0 {
+            if let Some(handoff) = L::shared(state).state().get_ref().hand_off_empty() {
+                handoff.complete(TupleList::into_tuple);
+            }
+            return;
+        }
+        // SAFETY: Recursion invariant holds.
+        unsafe { subops.start_list_by_ref() };
+    }
+
+    fn complete(state: StateRef<'_, Self, R>, value: Sum<SenderOutputList<L>>) {
+        let fan_in = L::shared(state).state().get_ref();
+        fan_in.gather().values.set(value);
+        if let Some(handoff) = fan_in.arrive() {
+            handoff.complete(TupleList::into_tuple);
+        }
+    }
+
+    fn error(state: StateRef<'_, Self, R>, error: Sum<SenderErrorList<L>>) {
+        let fan_in = L::shared(state).state().get_ref();
+        let gather = fan_in.gather();
+        let stored = <SenderErrorList<L> as SlotList>::store(&gather.errors, error);
+        assert!(stored, "the sub-sender completed twice");
+        gather.failed.store(true, Release);
+        if let Some(handoff) = fan_in.arrive() {
+            handoff.complete(TupleList::into_tuple);
+        }
+    }
+
+    fn stop(state: StateRef<'_, Self, R>) {
+        let fan_in = L::shared(state).state().get_ref();
+        fan_in.gather().values.set_stopped();
+        if let Some(handoff) = fan_in.arrive() {
+            handoff.complete(TupleList::into_tuple);
+        }
+    }
+}
+
+pub type WhenAllErrors<S> = BasicSender<WhenAllErrorsExpr<<S as Tuple>::TupleList>>;
+
+/// Runs a tuple of senders concurrently like [`when_all`], but if any of them
+/// fails, completes with the errors of all the failing senders, as a tuple of
+/// `Option`s indexed like the senders.
+///
+/// All the senders run to completion, and errors take precedence over stopped
+/// signals.
+pub fn when_all_errors<S>(senders: S) -> WhenAllErrors<S>
+where
+    S: Tuple<
+        TupleList: SenderList<OutputList: TupleList, ErrorList: SlotList<Stored: TupleList>>
+                       + ListPlace,
+    >,
+{
+    BasicSender::new((), senders.into_tuple_list())
+}
+
+/// Like [`WhenAllFailFastExpr`], but for sub-senders that output `Result`s with
+/// the same error type `E`, and also stops on the first `Err` among them.
+pub struct TryWhenAllExpr<L, E>(PhantomData<(L, E)>);
+
+/// The `Ok` values of [`TryWhenAll`], as a tuple indexed like the senders.
+pub type OksOf<L, E> = <OkList<L, E> as TupleList>::Tuple;
+type OkList<L, E> = <SenderOutputList<L> as ResultList<E>>::Oks;
+
+/// Gathers the `Ok` values of all the sub-senders of [`TryWhenAll`], or the
+/// first `Err` value.
+pub struct AllOks<L: SlotList, F, E> {
+    values: L::Slots,
+    failure: OnceSlot<F>,
+    error: OnceSlot<E>,
+    stopped: AtomicBool,
+}
+
+impl<L: SlotList, F, E> Gather for AllOks<L, F, E> {
+    type Value = Result<L, F>;
+    type Error = E;
+
+    fn take(&self) -> Completion<Result<L, F>, E> {
+        if let Some(error) = self.error.take() {
+            Completion::Error(error)
+        } else if let Some(failure) = self.failure.take() {
+            Completion::Value(Err(failure))
+        } else if self.stopped.load(Acquire) {
+            Completion::Stopped
+        } else {
+            let values = L::take_all(&self.values).expect("all the sub-senders have completed");
+            Completion::Value(Ok(values))
+        }
+    }
+}
+
+/// The shared state of [`TryWhenAll`].
+pub type TryState<L, F, E, R> = StopFanIn<AllOks<L, F, E>, R>;
+
+fn into_tuples<L: TupleList, F>(result: Result<L, F>) -> Result<L::Tuple, F> {
+    result.map(TupleList::into_tuple)
+}
+
+impl<L, E> SenderExpr for TryWhenAllExpr<L, E>
+where
+    L: SenderList<OutputList: ResultList<E, Oks: TupleList>> + ListPlace,
+{
+    type Output = Result<OksOf<L, E>, E>;
+    type Error = Sum<SenderErrorList<L>>;
+    type Stopped = SenderStoppedList<L>;
+    type Attrs = EmptyEnv;
+    type Data = ();
+    type SubSenders = L;
+
+    fn attrs(_: &(), _: &L) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<L, E, R> SenderExprTo<R> for TryWhenAllExpr<L, E>
+where
+    L: SenderList<OutputList: ResultList<E, Oks: TupleList + Send>> + ListPlace,
+    E: Send,
+    Self::Error: Send,
+    R: Receiver<Self::Output, Self::Error, Env: QueryStopToken + Send> + Send,
+{
+    type State = TryState<OkList<L, E>, E, Self::Error, R>;
+    type ConnectError = Infallible;
+    type SubEnv = StopEnv<EnvOf<R>>;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(_: (), _: &mut L, recv: R) -> Self::CreateState {
+        let gather = AllOks {
+            values: <OkList<L, E> as SlotList>::empty_slots(),
+            failure: OnceSlot::new(),
+            error: OnceSlot::new(),
+            stopped: AtomicBool::new(false),
+        };
+        let complete = |handoff: Handoff<Result<OkList<L, E>, E>, Self::Error, R>| {
+            handoff.complete(into_tuples)
+        };
+        let len = <OkList<L, E> as SlotList>::LEN;
+        init::with(move || StopFanIn::new(len, gather, recv, complete))
+    }
+
+    fn start(state: StateRef<'_, Self, R>, subops: Pin<&mut ConnectAllOps<Self, R>>)
+    where
+        State<Self, R>: ConnectAll<Self, R>,
+    {
+        let state = L::shared(state).state().get_ref();
+        // SAFETY: The state is pinned.
+        unsafe { state.start() };
+        if <OkList<L, E> as SlotList>::LEN == 0 {
+            state.finish_empty();
+            return;
+        }
+        // SAFETY: Recursion invariant holds.
+        unsafe { subops.start_list_by_ref() };
+    }
+
+    fn complete(state: StateRef<'_, Self, R>, value: Sum<SenderOutputList<L>>) {
+        let state = L::shared(state).state().get_ref();
+        let gather = state.gather();
+        match <SenderOutputList<L> as ResultList<E>>::store_ok(&gather.values, value) {
+            Ok(stored) => assert!(stored, "the sub-sender completed twice"),
+            Err(failure) => {
+                let _ = gather.failure.set(failure);
+                // The stop is requested before arriving, after which the state may be
+                // gone.
+                state.source().request_stop();
+            }
+        }
+        state.arrive();
+    }
+
+    fn error(state: StateRef<'_, Self, R>, error: Sum<SenderErrorList<L>>) {
+        let state = L::shared(state).state().get_ref();
+        let _ = state.gather().error.set(error);
+        state.source().request_stop();
+        state.arrive();
+    }
+
+    fn stop(state: StateRef<'_, Self, R>) {
+        let state = L::shared(state).state().get_ref();
+        state.gather().stopped.store(true, Release);
+        state.source().request_stop();
+        state.arrive();
+    }
+}
+
+pub type TryWhenAll<S, E> = BasicSender<TryWhenAllExpr<<S as Tuple>::TupleList, E>>;
+
+/// Runs a tuple of senders of `Result`s concurrently, and completes with the
+/// tuple of their `Ok` values, or with the first `Err` value.
+///
+/// Like [`when_all_fail_fast`], the others are requested to stop on the first
+/// `Err` value, error or stopped signal, and the operation completes after all
+/// of them. Errors take precedence over `Err` values, which take precedence
+/// over stopped signals.
+pub fn try_when_all<S, E>(senders: S) -> TryWhenAll<S, E>
+where
+    S: Tuple<TupleList: SenderList<OutputList: ResultList<E, Oks: TupleList>> + ListPlace>,
+{
+    BasicSender::new((), senders.into_tuple_list())
+}
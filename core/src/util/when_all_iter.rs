@@ -0,0 +1,322 @@
+use alloc::{
+    alloc::{alloc, dealloc, handle_alloc_error},
+    vec::Vec,
+};
+use core::{
+    alloc::Layout,
+    marker::{PhantomData, PhantomPinned},
+    mem::{self, ManuallyDrop},
+    pin::Pin,
+    ptr::{self, NonNull},
+    slice,
+};
+
+use placid::{
+    init::InitPinError,
+    pin::{DropSlot, DroppingSlot},
+    prelude::*,
+};
+
+use crate::{
+    OperationState, Receiver, Sender, SenderTo,
+    basic::{AllOf, FanIn, OnceSlot},
+    env::{EmptyEnv, EnvOf, GetEnv},
+};
+
+/// One allocation holding the sub-operations, followed by their result slots.
+struct Buffer<O, T> {
+    ptr: NonNull<u8>,
+    layout: Layout,
+    slots_offset: usize,
+    cap: usize,
+    // The number of the sub-operations initialized in order.
+    init: usize,
+    marker: PhantomData<(O, T)>,
+}
+
+// SAFETY: The buffer owns the operations and the slots.
+unsafe impl<O: Send, T: Send> Send for Buffer<O, T> {}
+// SAFETY: The slots are synchronized.
+unsafe impl<O: Sync, T: Send> Sync for Buffer<O, T> {}
+
+impl<O, T> Buffer<O, T> {
+    fn new(cap: usize) -> Self {
+        let (layout, slots_offset) = Layout::array::<O>(cap)
+            .and_then(|ops| ops.extend(Layout::array::<OnceSlot<T>>(cap)?))
+            .expect("capacity overflow");
+        let ptr = if layout.size() == 0 {
+            // SAFETY: The alignment is never zero.
+            unsafe { NonNull::new_unchecked(ptr::without_provenance_mut(layout.align())) }
+        } else {
+            // SAFETY: The layout has a non-zero size.
+            NonNull::new(unsafe { alloc(layout) }).unwrap_or_else(|| handle_alloc_error(layout))
+        };
+        let buffer = Buffer {
+            ptr,
+            layout,
+            slots_offset,
+            cap,
+            init: 0,
+            marker: PhantomData,
+        };
+        for index in 0..cap {
+            // SAFETY: The slot is in bounds.
+            unsafe { buffer.slots().add(index).write(OnceSlot::new()) };
+        }
+        buffer
+    }
+
+    fn op(&self, index: usize) -> *mut O {
+        debug_assert!(index < self.cap);
+        // SAFETY: The operation is in bounds.
+        unsafe { self.ptr.cast::<O>().add(index).as_ptr() }
+    }
+
+    fn slots(&self) -> NonNull<OnceSlot<T>> {
+        // SAFETY: The offset is computed from the layout.
+        unsafe { self.ptr.add(self.slots_offset).cast() }
+    }
+}
+
+impl<O, T> Drop for Buffer<O, T> {
+    fn drop(&mut self) {
+        // SAFETY: The first `init` operations and all the slots are initialized, and
+        // the allocation matches the layout.
+        unsafe {
+            (0..self.init).for_each(|index| self.op(index).drop_in_place());
+            ptr::slice_from_raw_parts_mut(self.slots().as_ptr(), self.cap).drop_in_place();
+            if self.layout.size() != 0 {
+                dealloc(self.ptr.as_ptr(), self.layout);
+            }
+        }
+    }
+}
+
+pub struct IterShared<T, E, R: GetEnv> {
+    // The values are stored into `slots`, so nothing is gathered but the first
+    // error or stopped signal.
+    fan_in: FanIn<AllOf<(), E>, R>,
+    slots: NonNull<OnceSlot<T>>,
+    len: usize,
+    env: EnvOf<R>,
+}
+
+// SAFETY: `slots` points into the buffer owned by the same operation state.
+unsafe impl<T: Send, E: Send, R: GetEnv<Env: Send> + Send> Send for IterShared<T, E, R> {}
+// SAFETY: Same as above, and the states are synchronized.
+unsafe impl<T: Send, E: Send, R: GetEnv<Env: Sync> + Send> Sync for IterShared<T, E, R> {}
+
+impl<T, E, R> IterShared<T, E, R>
+where
+    R: Receiver<Vec<T>, E>,
+{
+    fn arrive(&self) {
+        let Some(handoff) = self.fan_in.arrive() else {
+            return;
+        };
+        handoff.complete(|()| {
+            // SAFETY: The slots live in the buffer, which outlives all the receivers.
+            let slots = unsafe { slice::from_raw_parts(self.slots.as_ptr(), self.len) };
+            let values = slots.iter().map(|slot| slot.take().expect("value missing"));
+            values.collect()
+        });
+    }
+}
+
+/// The receiver of each sub-sender of [`WhenAllIter`].
+pub struct IterRecv<T, E, R: Receiver<Vec<T>, E>> {
+    // Effectively a `&'op IterShared<T, E, R>`.
+    shared: NonNull<IterShared<T, E, R>>,
+    index: usize,
+}
+
+// SAFETY: The shared state is synchronized, and the values, errors and the
+// receiver may be moved to other threads.
+unsafe impl<T, E, R> Send for IterRecv<T, E, R>
+where
+    T: Send,
+    E: Send,
+    R: Receiver<Vec<T>, E, Env: Sync> + Send,
+{
+}
+
+// SAFETY: Same as above.
+unsafe impl<T, E, R> Sync for IterRecv<T, E, R>
+where
+    T: Send,
+    E: Send,
+    R: Receiver<Vec<T>, E, Env: Sync> + Send,
+{
+}
+
+impl<T, E, R: Receiver<Vec<T>, E>> IterRecv<T, E, R> {
+    fn shared(&self) -> &IterShared<T, E, R> {
+        // SAFETY: The shared state lives in the operation state, which outlives all
+        // the sub-operations and thus their receivers.
+        unsafe { self.shared.as_ref() }
+    }
+}
+
+impl<T, E, R: Receiver<Vec<T>, E>> GetEnv for IterRecv<T, E, R> {
+    type Env = EnvOf<R>;
+
+    fn get_env(&self) -> EnvOf<R> {
+        self.shared().env.clone()
+    }
+}
+
+impl<T, E, R: Receiver<Vec<T>, E>> Receiver<T, E> for IterRecv<T, E, R> {
+    fn set(self, value: T) {
+        let shared = self.shared();
+        // SAFETY: The index is in bounds of the slots.
+        let slot = unsafe { shared.slots.add(self.index).as_ref() };
+        assert!(slot.set(value).is_ok(), "the sub-sender completed twice");
+        shared.arrive();
+    }
+
+    fn set_error(self, error: E) {
+        let shared = self.shared();
+        shared.fan_in.gather().set_error(error);
+        shared.arrive();
+    }
+
+    fn set_stopped(self) {
+        let shared = self.shared();
+        shared.fan_in.gather().set_stopped();
+        shared.arrive();
+    }
+}
+
+type Recv<S, R> = IterRecv<<S as Sender>::Output, <S as Sender>::Error, R>;
+
+/// The operation state of [`WhenAllIter`].
+pub struct WhenAllIterOp<S, R>
+where
+    S: SenderTo<Recv<S, R>>,
+    R: Receiver<Vec<S::Output>, S::Error>,
+{
+    // `buffer` must come before `shared`, since the sub-operations in it refer
+    // to `shared`.
+    buffer: Buffer<S::Operation, S::Output>,
+    shared: IterShared<S::Output, S::Error, R>,
+    _marker: PhantomPinned,
+}
+
+unsafe impl<S, R> OperationState for WhenAllIterOp<S, R>
+where
+    S: SenderTo<Recv<S, R>>,
+    R: Receiver<Vec<S::Output>, S::Error>,
+{
+    unsafe fn start_by_ref(self: Pin<&mut Self>) {
+        // The sub-operations are accessed through raw pointers, since the shared state
+        // may be concurrently accessed by the sub-operations that have started.
+        let this = self.into_ref().get_ref();
+        for index in 0..this.buffer.init {
+            // SAFETY: Each sub-operation is pinned in the buffer, started only once here,
+            // and not forgotten as long as this operation is not.
+            unsafe { Pin::new_unchecked(&mut *this.buffer.op(index)).start_by_ref() };
+        }
+        this.shared.arrive();
+    }
+}
+
+/// A sender that runs all the senders from an iterator concurrently, and
+/// completes with their values in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WhenAllIter<I>(I);
+
+impl<I> GetEnv for WhenAllIter<I> {
+    type Env = EmptyEnv;
+
+    fn get_env(&self) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<I, S> Sender for WhenAllIter<I>
+where
+    I: IntoIterator<Item = S, IntoIter: ExactSizeIterator>,
+    S: Sender,
+{
+    type Output = Vec<S::Output>;
+    type Error = S::Error;
+    type Stopped = S::Stopped;
+}
+
+impl<I, S, R> SenderTo<R> for WhenAllIter<I>
+where
+    I: IntoIterator<Item = S, IntoIter: ExactSizeIterator>,
+    S: SenderTo<Recv<S, R>>,
+    R: Receiver<Vec<S::Output>, S::Error>,
+{
+    type Operation = WhenAllIterOp<S, R>;
+    type ConnectError = S::ConnectError;
+
+    fn connect(self, receiver: R) -> impl InitPin<Self::Operation, Error = Self::ConnectError> {
+        init::try_raw_pin(move |mut uninit: Uninit<Self::Operation>, slot| unsafe {
+            // SAFETY: Like `BasicOperation`, the receivers of the sub-operations point
+            // to `shared`, which is dropped after `buffer`.
+            let iter = self.0.into_iter();
+            let cap = iter.len();
+            let buffer = Buffer::<S::Operation, S::Output>::new(cap);
+
+            let ptr = uninit.as_mut_ptr();
+            let shared = &raw mut (*ptr).shared;
+            shared.write(IterShared {
+                slots: buffer.slots(),
+                len: 0,
+                env: receiver.get_env(),
+                // One more arrival for the starter.
+                fan_in: FanIn::new(cap + 1, AllOf::new(), receiver),
+            });
+            (&raw mut (*ptr).buffer).write(buffer);
+            (&raw mut (*ptr)._marker).write(PhantomPinned);
+
+            let buffer = &raw mut (*ptr).buffer;
+            for sender in iter.take(cap) {
+                let index = (*buffer).init;
+                let recv = IterRecv {
+                    shared: NonNull::new_unchecked(shared),
+                    index,
+                };
+
+                let mut subslot = ManuallyDrop::new(DroppingSlot::new());
+                let subslot_ref = DropSlot::new_unchecked(&mut subslot);
+                let init = sender.connect(recv);
+                match Uninit::from_raw((*buffer).op(index)).try_write_pin(init, subslot_ref) {
+                    Ok(p) => {
+                        mem::forget(p);
+                        (*buffer).init += 1;
+                    }
+                    Err(err) => {
+                        buffer.drop_in_place();
+                        shared.drop_in_place();
+                        return Err(InitPinError::new(err.error, uninit, slot));
+                    }
+                }
+            }
+            // Balance the arrivals of the sub-senders that the iterator did not yield.
+            let len = (*buffer).init;
+            for _ in len..cap {
+                let handoff = (*shared).fan_in.arrive();
+                debug_assert!(handoff.is_none());
+            }
+            (*shared).len = len;
+
+            Ok(uninit.assume_init_pin(slot))
+        })
+    }
+}
+
+/// Runs the senders from the iterator concurrently, and completes with the
+/// `Vec` of their values in the order of the iterator.
+///
+/// The operation state allocates one buffer for all the sub-operations and
+/// their results, sized from the length of the iterator.
+pub fn when_all_iter<I, S>(iter: I) -> WhenAllIter<I>
+where
+    I: IntoIterator<Item = S, IntoIter: ExactSizeIterator>,
+    S: Sender,
+{
+    WhenAllIter(iter)
+}
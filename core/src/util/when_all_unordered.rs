@@ -0,0 +1,131 @@
+use core::{convert::Infallible, marker::PhantomData, pin::Pin};
+
+use placid::prelude::*;
+use tsum::Sum;
+use tuple_list::Tuple;
+
+use crate::{
+    Receiver,
+    basic::*,
+    env::EmptyEnv,
+    list::{
+        CountList, OperationStateList, SenderErrorList, SenderList, SenderOutputList,
+        SenderStoppedList, UniformList,
+    },
+};
+
+/// Receives the values of [`WhenAllUnordered`] with the indices of their
+/// sub-senders, in the order they complete.
+///
+/// The sub-senders may complete concurrently, so the items may be received
+/// from different threads at once.
+pub trait ItemReceiver<T> {
+    fn set_item(&self, index: usize, item: T);
+}
+
+impl<T, F: Fn(usize, T)> ItemReceiver<T> for F {
+    fn set_item(&self, index: usize, item: T) {
+        self(index, item)
+    }
+}
+
+/// Delivers the values of the sub-senders to an [`ItemReceiver`] as they
+/// complete, and completes after all of them.
+pub struct WhenAllUnorderedExpr<L, F, T>(PhantomData<(L, F, T)>);
+
+/// The shared state of [`WhenAllUnordered`].
+pub struct UnorderedState<F, E, R> {
+    items: F,
+    // The values are handed to `items` right away, so nothing is gathered but
+    // the first error or stopped signal.
+    fan_in: FanIn<AllOf<(), E>, R>,
+}
+
+impl<F, E, R: Receiver<(), E>> UnorderedState<F, E, R> {
+    fn arrive(&self) {
+        if let Some(handoff) = self.fan_in.arrive() {
+            handoff.complete(drop);
+        }
+    }
+}
+
+impl<L, F, T> SenderExpr for WhenAllUnorderedExpr<L, F, T>
+where
+    L: SenderList<OutputList: UniformList<T>> + ListPlace,
+    F: ItemReceiver<T>,
+{
+    type Output = ();
+    type Error = Sum<SenderErrorList<L>>;
+    type Stopped = SenderStoppedList<L>;
+    type Attrs = EmptyEnv;
+    type Data = F;
+    type SubSenders = L;
+
+    fn attrs(_: &F, _: &L) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<L, F, T, R> SenderExprTo<R> for WhenAllUnorderedExpr<L, F, T>
+where
+    L: SenderList<OutputList: UniformList<T>> + ListPlace,
+    F: ItemReceiver<T>,
+    R: Receiver<(), Self::Error>,
+{
+    type State = UnorderedState<F, Self::Error, R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(items: F, _: &mut L, recv: R) -> Self::CreateState {
+        // One more arrival for the starter.
+        init::with(move || UnorderedState {
+            items,
+            fan_in: FanIn::new(<L as CountList>::LEN + 1, AllOf::new(), recv),
+        })
+    }
+
+    fn start(state: StateRef<'_, Self, R>, subops: Pin<&mut ConnectAllOps<Self, R>>)
+    where
+        State<Self, R>: ConnectAll<Self, R>,
+    {
+        // SAFETY: Recursion invariant holds.
+        unsafe { subops.start_list_by_ref() };
+        L::shared(state).state().get_ref().arrive();
+    }
+
+    fn complete(state: StateRef<'_, Self, R>, value: Sum<SenderOutputList<L>>) {
+        let state = L::shared(state).state().get_ref();
+        let (index, item) = UniformList::into_indexed(value);
+        state.items.set_item(index, item);
+        state.arrive();
+    }
+
+    fn error(state: StateRef<'_, Self, R>, error: Sum<SenderErrorList<L>>) {
+        let state = L::shared(state).state().get_ref();
+        state.fan_in.gather().set_error(error);
+        state.arrive();
+    }
+
+    fn stop(state: StateRef<'_, Self, R>) {
+        let state = L::shared(state).state().get_ref();
+        state.fan_in.gather().set_stopped();
+        state.arrive();
+    }
+}
+
+pub type WhenAllUnordered<S, F, T> =
+    BasicSender<WhenAllUnorderedExpr<<S as Tuple>::TupleList, F, T>>;
+
+/// Runs a tuple of senders of the same output type concurrently, and hands
+/// each value to `items` together with the index of its sender as soon as it
+/// arrives.
+///
+/// Completes after all the senders do, with the first error or stopped signal
+/// among them if any.
+pub fn when_all_unordered<S, F, T>(senders: S, items: F) -> WhenAllUnordered<S, F, T>
+where
+    S: Tuple<TupleList: SenderList<OutputList: UniformList<T>> + ListPlace>,
+    F: ItemReceiver<T>,
+{
+    BasicSender::new(items, senders.into_tuple_list())
+}
@@ -0,0 +1,141 @@
+use core::{
+    convert::{self, Infallible},
+    marker::PhantomData,
+    pin::Pin,
+};
+
+use placid::prelude::*;
+use tsum::Sum;
+use tuple_list::Tuple;
+
+use crate::{
+    Receiver,
+    basic::*,
+    completion::{Completion, Yes},
+    env::{EmptyEnv, EnvOf, QueryStopToken, StopEnv},
+    list::{CountList, OperationStateList, SenderErrorList, SenderList, SenderOutputList},
+};
+
+/// Completes with the first completion of the sub-senders, after requesting
+/// the others to stop and waiting for them.
+pub struct WhenAnyExpr<L>(PhantomData<L>);
+
+/// Gathers the first value or error of the sub-senders of [`WhenAny`], or a
+/// stopped signal if there is none.
+pub struct FirstOf<T, E>(OnceSlot<Result<T, E>>);
+
+impl<T, E> Gather for FirstOf<T, E> {
+    type Value = T;
+    type Error = E;
+
+    fn take(&self) -> Completion<T, E> {
+        match self.0.take() {
+            Some(Ok(value)) => Completion::Value(value),
+            Some(Err(error)) => Completion::Error(error),
+            None => Completion::Stopped,
+        }
+    }
+}
+
+/// The shared state of [`WhenAny`].
+pub type WhenAnyState<T, E, R> = StopFanIn<FirstOf<T, E>, R>;
+
+/// Records the first value or error, and requests the others to stop.
+fn settle<T, E, R>(state: &WhenAnyState<T, E, R>, result: Result<T, E>)
+where
+    T: Send,
+    E: Send,
+    R: Receiver<T, E, Env: QueryStopToken + Send> + Send,
+{
+    if state.gather().0.set(result).is_ok() {
+        state.source().request_stop();
+    }
+    state.arrive();
+}
+
+impl<L> SenderExpr for WhenAnyExpr<L>
+where
+    L: SenderList + ListPlace,
+{
+    type Output = Sum<SenderOutputList<L>>;
+    type Error = Sum<SenderErrorList<L>>;
+    // Completes with a stopped signal if all the sub-senders do, or if there is
+    // none.
+    type Stopped = Yes;
+    type Attrs = EmptyEnv;
+    type Data = ();
+    type SubSenders = L;
+
+    fn attrs(_: &(), _: &L) -> EmptyEnv {
+        EmptyEnv
+    }
+}
+
+impl<L, R> SenderExprTo<R> for WhenAnyExpr<L>
+where
+    L: SenderList + ListPlace,
+    Self::Output: Send,
+    Self::Error: Send,
+    R: Receiver<Self::Output, Self::Error, Env: QueryStopToken + Send> + Send,
+{
+    type State = WhenAnyState<Self::Output, Self::Error, R>;
+    type ConnectError = Infallible;
+    type SubEnv = StopEnv<EnvOf<R>>;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(_: (), _: &mut L, recv: R) -> Self::CreateState {
+        // One more arrival for the starter, so that the operation completes after
+        // all the sub-operations have started, or right away if there is none.
+        let complete =
+            |handoff: Handoff<Self::Output, Self::Error, R>| handoff.complete(convert::identity);
+        init::with(move || {
+            StopFanIn::new(
+                <L as CountList>::LEN + 1,
+                FirstOf(OnceSlot::new()),
+                recv,
+                complete,
+            )
+        })
+    }
+
+    fn start(state: StateRef<'_, Self, R>, subops: Pin<&mut ConnectAllOps<Self, R>>)
+    where
+        State<Self, R>: ConnectAll<Self, R>,
+    {
+        let state = L::shared(state).state().get_ref();
+        // SAFETY: The state is pinned.
+        unsafe { state.start() };
+        // SAFETY: Recursion invariant holds.
+        unsafe { subops.start_list_by_ref() };
+        state.arrive();
+    }
+
+    fn complete(state: StateRef<'_, Self, R>, value: Sum<SenderOutputList<L>>) {
+        settle(L::shared(state).state().get_ref(), Ok(value));
+    }
+
+    fn error(state: StateRef<'_, Self, R>, error: Sum<SenderErrorList<L>>) {
+        settle(L::shared(state).state().get_ref(), Err(error));
+    }
+
+    fn stop(state: StateRef<'_, Self, R>) {
+        L::shared(state).state().get_ref().arrive();
+    }
+}
+
+pub type WhenAny<S> = BasicSender<WhenAnyExpr<<S as Tuple>::TupleList>>;
+
+/// Runs a tuple of senders concurrently, and completes with the first value or
+/// error among them, tagged by the sender it comes from.
+///
+/// The sub-senders observe the stop token of a stop source in the operation
+/// state, which is requested as soon as the first of them completes, or when
+/// the receiver requests a stop. The operation completes after all the others
+/// acknowledge the request, and with a stopped signal if all of them complete
+/// that way.
+pub fn when_any<S>(senders: S) -> WhenAny<S>
+where
+    S: Tuple<TupleList: SenderList + ListPlace>,
+{
+    BasicSender::new((), senders.into_tuple_list())
+}
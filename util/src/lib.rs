@@ -1,10 +1,8 @@
 #![no_std]
-#![cfg_attr(feature = "alloc", feature(min_specialization))]
-#![cfg_attr(feature = "alloc", feature(trusted_len))]
+#![feature(impl_trait_in_assoc_type)]
 
 pub mod sender;
 
-#[cfg(feature = "alloc")]
 extern crate alloc;
 
 #[cfg(feature = "std")]
@@ -1,48 +1,42 @@
-mod bind;
 mod eager;
-#[cfg(feature = "alloc")]
 mod join_all;
-#[cfg(feature = "alloc")]
 mod join_tuple;
-mod map;
 mod sched;
 mod sched_on;
-#[cfg(feature = "alloc")]
 mod select;
+mod spawn;
 mod transfer;
-mod value;
-#[cfg(feature = "alloc")]
+#[cfg(feature = "std")]
 mod wait;
 
-use rxec_core::Sender;
+use core::convert::Infallible;
+
+pub use rxec_core::util::{AndThen as Bind, Map, Value, and_then as bind, map, value};
+use rxec_core::{Scheduler, Sender};
 
 pub use self::{
-    bind::{Bind, bind},
     eager::{Eager, eager},
-    map::{Map, map},
-    sched::{Scheduler, schedule},
-    sched_on::{SchedOn, sched_on},
-    transfer::{Transfer, transfer},
-    value::{Value, value},
-};
-#[cfg(feature = "alloc")]
-pub use self::{
     join_all::{JoinAll, JoinAllExt, join_all},
     join_tuple::{JoinTuple, JoinTupleExt, join, join_tuple},
-    select::{Select, select},
-    wait::Async,
+    sched::schedule,
+    sched_on::{SchedOn, SchedOnExpr, sched_on},
+    select::{Select, SelectExpr, select},
+    spawn::spawn,
+    transfer::{Transfer, TransferExpr, transfer},
 };
 #[cfg(feature = "std")]
 pub use self::{
-    sched::{ArcLoop, ArcLoopExec, ArcLoopSender, Loop, LoopExec, LoopSender},
+    sched::{Loop, LoopScheduler, LoopTask, LoopTaskExpr},
     wait::wait,
 };
 
+const ONESHOT_COMPLETED: &str = "oneshot sender already completed";
+
 pub trait SenderExt: Sender + Sized {
     fn and_then<F, T>(self, f: F) -> Bind<Self, F>
     where
         F: FnOnce(Self::Output) -> T,
-        T: Sender,
+        T: Sender<Error = Self::Error>,
     {
         bind(self, f)
     }
@@ -60,19 +54,18 @@ pub trait SenderExt: Sender + Sized {
 
     fn sched_on<Sched>(self, sched: Sched) -> SchedOn<Self, Sched>
     where
-        Sched: Scheduler,
+        Sched: Scheduler<Task: Sender<Error = Infallible>>,
     {
         sched_on(self, sched)
     }
 
     fn transfer<Sched>(self, sched: Sched) -> Transfer<Self, Sched>
     where
-        Sched: Scheduler,
+        Sched: Scheduler<Task: Sender<Error = Infallible>> + Clone,
     {
         transfer(self, sched)
     }
 
-    #[cfg(feature = "alloc")]
     fn join<T>(self, other: T) -> JoinTuple<(Self, T)>
     where
         T: Sender,
@@ -80,7 +73,6 @@ pub trait SenderExt: Sender + Sized {
         join(self, other)
     }
 
-    #[cfg(feature = "alloc")]
     fn or<T>(self, other: T) -> Select<Self, T>
     where
         T: Sender,
@@ -104,12 +96,44 @@ macro_rules! exec {
     }};
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use alloc::string::String;
-    use std::{print, println};
+    use core::convert::Infallible;
+    use std::{print, println, sync::Mutex, thread};
+
+    use placid::pown;
+    use rxec_core::{
+        OperationState, Receiver, Scheduler, SenderTo,
+        env::{EmptyEnv, GetEnv},
+    };
+
+    use super::{Loop, SenderExt, spawn, value, wait};
+
+    /// Records how the operation completes, borrowing the record.
+    struct Record<'a>(&'a Mutex<Option<&'static str>>);
+
+    impl GetEnv for Record<'_> {
+        type Env = EmptyEnv;
+
+        fn get_env(&self) -> EmptyEnv {
+            EmptyEnv
+        }
+    }
+
+    impl Receiver<()> for Record<'_> {
+        fn set(self, (): ()) {
+            *self.0.lock().unwrap() = Some("value");
+        }
 
-    use super::{Loop, Scheduler, SenderExt, value, wait};
+        fn set_error(self, error: Infallible) {
+            match error {}
+        }
+
+        fn set_stopped(self) {
+            *self.0.lock().unwrap() = Some("stopped");
+        }
+    }
 
     #[test]
     fn basic() {
@@ -118,10 +142,10 @@ mod tests {
         let work1 = value(String::from("Hello"))
             .map(|s| print!("{s} "))
             .map(|_| 1)
-            .sched_on(&rl);
+            .sched_on(rl.scheduler());
 
         let work2 = exec! {
-            let _ = @rl.schedule();
+            let _ = @rl.scheduler().schedule();
             let s = String::from("World");
             let _ = println!("{s}");
             2
@@ -130,4 +154,126 @@ mod tests {
         let (r1, r2) = wait(work1.join(work2));
         assert_eq!((r1, r2), (1, 2));
     }
+
+    #[test]
+    fn transfer_errors() {
+        use rxec_core::{
+            completion::Completion,
+            util::{dematerialize, upon_error},
+        };
+
+        let rl = Loop::new();
+        let caller = thread::current().id();
+        // The error is delivered on the loop, like a value would be.
+        let s = dematerialize(value(Completion::<i32, i32>::Error(1))).transfer(rl.scheduler());
+        let s = upon_error(s, move |e| e + i32::from(thread::current().id() != caller));
+        assert_eq!(wait(s), 2);
+    }
+
+    #[test]
+    fn spawn_detached() {
+        let rl = Loop::new();
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        spawn(rl.scheduler(), value(()).map(move |()| tx.send(7).unwrap()));
+        assert_eq!(rx.recv().unwrap(), 7);
+    }
+
+    #[test]
+    fn loop_tasks_in_place() {
+        let rl = Loop::new();
+        let record = Mutex::new(None);
+        {
+            let op = pown!(rl.scheduler().schedule().connect(Record(&record)));
+            OperationState::start(op);
+            // The operation may be dropped while the task is still running.
+            while record.lock().unwrap().is_none() {
+                thread::yield_now();
+            }
+        }
+        assert_eq!(record.lock().unwrap().take(), Some("value"));
+
+        // Hold the loop until the queued task below is torn down.
+        let (tx, rx) = std::sync::mpsc::channel();
+        spawn(rl.scheduler(), value(()).map(move |()| rx.recv().unwrap()));
+        {
+            let op = pown!(rl.scheduler().schedule().connect(Record(&record)));
+            OperationState::start(op);
+        }
+        tx.send(()).unwrap();
+
+        let sched = rl.scheduler();
+        drop(rl);
+        assert_eq!(record.lock().unwrap().take(), None);
+        {
+            let op = pown!(sched.schedule().connect(Record(&record)));
+            OperationState::start(op);
+        }
+        assert_eq!(record.lock().unwrap().take(), Some("stopped"));
+    }
+
+    #[test]
+    fn loop_tasks_stop() {
+        use rxec_core::{list::split_first, util::when_any};
+
+        let rl = Loop::new();
+        // Hold the loop, so that the task below is still queued when stopped.
+        let (tx, rx) = std::sync::mpsc::channel();
+        spawn(rl.scheduler(), value(()).map(move |()| rx.recv().unwrap()));
+
+        // The value wins, and the task is removed from the queue.
+        let s = when_any((rl.scheduler().schedule(), value(1)));
+        assert!(split_first(wait(s)).is_err());
+        tx.send(()).unwrap();
+    }
+
+    #[test]
+    fn unordered_completion_order() {
+        use rxec_core::util::when_all_unordered;
+
+        let rl = Loop::new();
+        // Hold the loop, so that the first sender finishes after the second one.
+        let (tx, rx) = std::sync::mpsc::channel();
+        spawn(rl.scheduler(), value(()).map(move |()| rx.recv().unwrap()));
+
+        let items = Mutex::new(alloc::vec::Vec::new());
+        let senders = (value(1).sched_on(rl.scheduler()), value(2));
+        let s = when_all_unordered(senders, |index: usize, v: i32| {
+            items.lock().unwrap().push((index, v));
+            if index == 1 {
+                tx.send(()).unwrap();
+            }
+        });
+        wait(s);
+        assert_eq!(items.into_inner().unwrap(), [(1, 2), (0, 1)]);
+    }
+
+    #[test]
+    fn catch_worker_panics() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        use rxec_core::util::{CatchError, WaitError, catch_unwind, sync_wait, sync_wait_unwind};
+
+        let rl = Loop::new();
+        // The adaptor is started on the worker, where the work panics.
+        let work = catch_unwind(value(1).map(|i: i32| -> i32 { panic!("boom {i}") }));
+        let s = work.sched_on(rl.scheduler());
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| sync_wait_unwind(s))).unwrap_err();
+        assert_eq!(payload.downcast_ref::<String>().unwrap(), "boom 1");
+
+        // The sibling queued on the loop is torn down before the panic completes
+        // the adaptor.
+        let (tx, rx) = std::sync::mpsc::channel();
+        spawn(rl.scheduler(), value(()).map(move |()| rx.recv().unwrap()));
+        let queued = value(2).sched_on(rl.scheduler());
+        let s = catch_unwind(queued.join(value(3).map(|i: i32| -> i32 { panic!("boom {i}") })));
+        assert!(matches!(
+            sync_wait(s),
+            Err(WaitError::Error(CatchError::Panic(_)))
+        ));
+        tx.send(()).unwrap();
+
+        // The worker survives.
+        assert_eq!(wait(rl.scheduler().schedule().map(|()| 4)), 4);
+    }
 }
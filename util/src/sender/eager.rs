@@ -1,14 +1,51 @@
-use rxec_core::{Execution, ReceiverFrom, Sender, SenderTo};
+use core::{
+    mem::{self, ManuallyDrop},
+    pin::Pin,
+};
+
+use pin_project::pin_project;
+use placid::{
+    init::InitPinError,
+    pin::{DropSlot, DroppingSlot},
+    prelude::*,
+};
+use rxec_core::{
+    OperationState, ReceiverFrom, Sender, SenderTo,
+    env::{EnvOf, GetEnv},
+};
 
 pub fn eager<S: Sender>(s: S) -> Eager<S> {
     Eager(s)
 }
 
+/// A sender that starts its operation as soon as it is connected.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Eager<S: Sender>(S);
 
+impl<S: Sender> GetEnv for Eager<S> {
+    type Env = EnvOf<S>;
+
+    fn get_env(&self) -> EnvOf<S> {
+        self.0.get_env()
+    }
+}
+
 impl<S: Sender> Sender for Eager<S> {
     type Output = S::Output;
+    type Error = S::Error;
+    type Stopped = S::Stopped;
+}
+
+#[pin_project]
+pub struct Exec<O> {
+    #[pin]
+    inner: O,
+}
+
+// SAFETY: The inner operation is started on connection, and starting this one
+// does nothing.
+unsafe impl<O: OperationState> OperationState for Exec<O> {
+    unsafe fn start_by_ref(self: Pin<&mut Self>) {}
 }
 
 impl<S, R> SenderTo<R> for Eager<S>
@@ -16,9 +53,28 @@ where
     S: SenderTo<R>,
     R: ReceiverFrom<S>,
 {
-    type Execution = ();
+    type Operation = Exec<S::Operation>;
+    type ConnectError = S::ConnectError;
+
+    fn connect(self, receiver: R) -> impl InitPin<Self::Operation, Error = Self::ConnectError> {
+        init::try_raw_pin(move |mut uninit: Uninit<Self::Operation>, slot| unsafe {
+            let inner = &raw mut (*uninit.as_mut_ptr()).inner;
+            {
+                let mut subslot = ManuallyDrop::new(DroppingSlot::new());
+                let subslot_ref = DropSlot::new_unchecked(&mut subslot);
+
+                let init = self.0.connect(receiver);
+                match Uninit::from_raw(inner).try_write_pin(init, subslot_ref) {
+                    Ok(p) => mem::forget(p),
+                    Err(err) => return Err(InitPinError::new(err.error, uninit, slot)),
+                }
+            }
 
-    fn connect(self, receiver: R) {
-        self.0.connect(receiver).execute();
+            let mut op = uninit.assume_init_pin(slot);
+            // SAFETY: The inner operation is started only here, and it is dropped
+            // along with `op` in `slot` even if `op` is forgotten.
+            op.as_mut().project().inner.start_by_ref();
+            Ok(op)
+        })
     }
 }
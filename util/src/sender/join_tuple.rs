@@ -1,31 +1,24 @@
-mod aux;
-
-use either_slot::{
-    array::Element,
-    tuple::{self, Concat, Count, CountOf, InElement, Index, List, Place, TakeList, Whole},
-};
 use rxec_core::{
-    Execution, Receiver, Sender, SenderTo,
-    tuple_list::{ExecutionList, SenderList, SenderListTo},
+    Sender,
+    basic::ListPlace,
+    list::SenderList,
+    util::{WhenAll, when_all},
 };
 use tuple_list::{Tuple, TupleList};
 
-use self::aux::{ConstructReceiver, ReceiverList, ZipOption};
-
 pub fn join<S1, S2>(s1: S1, s2: S2) -> JoinTuple<(S1, S2)>
 where
     S1: Sender,
     S2: Sender,
 {
-    JoinTuple((s1, s2))
+    join_tuple((s1, s2))
 }
 
 pub fn join_tuple<S>(s: S) -> JoinTuple<S>
 where
-    S: Tuple,
-    S::TupleList: SenderList,
+    S: Tuple<TupleList: SenderList<OutputList: TupleList> + ListPlace>,
 {
-    JoinTuple(s)
+    when_all(s)
 }
 
 #[macro_export]
@@ -38,118 +31,14 @@ macro_rules! join {
 pub trait JoinTupleExt: Tuple {
     fn join(self) -> JoinTuple<Self>
     where
-        Self::TupleList: SenderList,
+        Self::TupleList: SenderList<OutputList: TupleList> + ListPlace,
     {
         join_tuple(self)
     }
 }
 impl<T: Tuple> JoinTupleExt for T {}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct JoinTuple<S>(S);
-
-impl<S> Sender for JoinTuple<S>
-where
-    S: Tuple,
-    S::TupleList: SenderList,
-{
-    type Output = <<S::TupleList as SenderList>::Output as TupleList>::Tuple;
-}
-
-impl<R, S, Z, O> SenderTo<R> for JoinTuple<S>
-where
-    R: Receiver<O>,
-    S: Tuple,
-    S::TupleList: SenderListTo<Z>,
-    <S::TupleList as SenderList>::Output: TupleList<Tuple = O>,
-    Z: TupleList,
-    O: Concat<(R,)> + ConstructReceiver<R, Receiver = Z>,
-    O::Output: Concat<()>,
-    List<O, R, ()>: InElement,
-    Whole<O, R, ()>: ReceiverList,
-    TakeList<O, R, ()>: ZipOption<Zipped = List<O, R, ()>>,
-    O::TupleList: Count,
-    Place<O, R, ()>: Index<CountOf<O>, Output = Element<R>>,
-{
-    type Execution = Exec<R, S::TupleList, Z, O>;
-
-    fn connect(self, receiver: R) -> Self::Execution {
-        let (rr, rlist) = O::construct();
-        let elist = self.0.into_tuple_list().connect_list(rlist);
-        Exec { elist, receiver, rr }
-    }
-}
-
-#[derive(Debug)]
-pub struct Exec<R, S, Z, O>
-where
-    R: Receiver<O>,
-    S: SenderListTo<Z>,
-    S::Output: TupleList<Tuple = O>,
-    Z: TupleList,
-    O: Concat<(R,)>,
-    O::Output: Concat<()>,
-    List<O, R, ()>: InElement,
-{
-    elist: S::ExecutionList,
-    receiver: R,
-    rr: tuple::Sender<O, R, ()>,
-}
-
-impl<R, S, Z, O> Execution for Exec<R, S, Z, O>
-where
-    R: Receiver<O>,
-    S: SenderListTo<Z>,
-    S::Output: TupleList<Tuple = O>,
-    Z: TupleList,
-    O: Concat<(R,)>,
-    O::Output: Concat<()>,
-    List<O, R, ()>: InElement,
-    Whole<O, R, ()>: ReceiverList,
-    TakeList<O, R, ()>: ZipOption<Zipped = List<O, R, ()>>,
-    O::TupleList: Count,
-    Place<O, R, ()>: Index<CountOf<O>, Output = Element<R>>,
-{
-    fn execute(self) {
-        self.elist.execute_list();
-        if let Err(rr) = self.rr.send(self.receiver)
-            && let Some(tlist) = rr.into_tuple_list().zip_option()
-        {
-            ReceiverList::consume(tlist.into_tuple());
-        }
-    }
-}
-
-pub struct Recv<R, Head, Current, Tail>
-where
-    Head: Concat<(Current,)>,
-    Tail: Concat<(R,)>,
-    Head::Output: Concat<Tail::Output>,
-    List<Head, Current, Tail::Output>: InElement,
-{
-    rr: tuple::Sender<Head, Current, <Tail as Concat<(R,)>>::Output>,
-}
-
-impl<R, Head, Current, Tail> Receiver<Current> for Recv<R, Head, Current, Tail>
-where
-    Head: Concat<(Current,)>,
-    Tail: Concat<(R,)>,
-    Head::Output: Concat<Tail::Output> + Concat<Tail>,
-    List<Head, Current, Tail::Output>: InElement,
-    TakeList<Head, Current, Tail::Output>: ZipOption<Zipped = List<Head, Current, Tail::Output>>,
-    <Head as Tuple>::TupleList: Count,
-    Place<Head, Current, Tail::Output>: Index<CountOf<Head>, Output = Element<Current>>,
-    R: Receiver<Whole<Head, Current, Tail>>,
-    Whole<Head, Current, Tail::Output>: ReceiverList,
-{
-    fn receive(self, value: Current) {
-        if let Err(rr) = self.rr.send(value)
-            && let Some(tlist) = rr.into_tuple_list().zip_option()
-        {
-            ReceiverList::consume(tlist.into_tuple());
-        }
-    }
-}
+pub type JoinTuple<S> = WhenAll<S>;
 
 #[cfg(all(test, feature = "std"))]
 mod tests {
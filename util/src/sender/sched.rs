@@ -1,60 +1,190 @@
-use rxec_core::Sender;
+use rxec_core::Scheduler;
 
-pub trait Scheduler {
-    type Sender: Sender<Output = ()>;
-
-    fn schedule(self) -> Self::Sender;
-}
-
-pub fn schedule<S: Scheduler>(s: S) -> S::Sender {
+pub fn schedule<S: Scheduler>(s: &S) -> S::Task {
     s.schedule()
 }
 
 #[cfg(feature = "std")]
 mod run_loop {
-    use alloc::{boxed::Box, collections::VecDeque, sync::Arc};
+    use alloc::{collections::VecDeque, sync::Arc};
     use core::{
-        iter,
-        sync::atomic::{AtomicBool, Ordering::SeqCst},
+        cell::{Cell, UnsafeCell},
+        convert::Infallible,
+        fmt,
+        marker::PhantomPinned,
+        pin::Pin,
+        ptr::NonNull,
+        sync::atomic::{AtomicBool, AtomicU8, Ordering::*},
     };
     use std::{
-        sync::{Condvar, Mutex},
-        thread::JoinHandle,
+        sync::{Condvar, Mutex, MutexGuard},
+        thread::{self, JoinHandle, ThreadId},
     };
 
-    use rxec_core::{Execution, Receiver, ReceiverFrom, Sender, SenderTo};
+    use placid::prelude::*;
+    use rxec_core::{
+        Receiver, Scheduler,
+        basic::*,
+        completion::Yes,
+        env::{EnvOf, GetEnv, QueryStopToken, SchedulerAttrs},
+        stop::{EnvStopCallback, OnStop},
+    };
 
-    use super::Scheduler;
+    use crate::sender::ONESHOT_COMPLETED;
+
+    const IDLE: u8 = 0;
+    const QUEUED: u8 = 1;
+    const RUNNING: u8 = 2;
+    const DONE: u8 = 3;
+    // Stopped before it is queued.
+    const CANCELED: u8 = 4;
+
+    /// A task of a [`Loop`], which lives in place in the operation state.
+    struct TaskNode {
+        // Guarded by the lock of the queue, except for the store of `DONE`.
+        state: AtomicU8,
+        // The thread running the task, and a flag on its stack that is set if the
+        // task is dropped from within itself. Guarded by the lock of the queue.
+        runner: UnsafeCell<Option<(ThreadId, *const Cell<bool>)>>,
+        // Called with `true` to run the task, or `false` to cancel it.
+        run: unsafe fn(NonNull<TaskNode>, bool),
+    }
 
-    type BoxedRecv = Box<dyn FnOnce() + Send>;
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    struct Task(NonNull<TaskNode>);
+
+    // SAFETY: The operation states of the tasks are `Send`, and the nodes are
+    // only accessed with the lock of the queue held, or by their runners.
+    unsafe impl Send for Task {}
+
+    type Queue<'a> = MutexGuard<'a, VecDeque<Task>>;
+
+    impl Task {
+        /// Runs or cancels the task, which has just been dequeued with the lock
+        /// of the queue held.
+        fn run(self, queue: Queue<'_>, run: bool) {
+            let removed = Cell::new(false);
+            // SAFETY: The task is valid until it is dropped, which waits for us
+            // unless it happens on this thread, in which case `removed` is set and
+            // the node must not be touched anymore.
+            unsafe {
+                let node = self.0.as_ref();
+                node.state.store(RUNNING, Relaxed);
+                *node.runner.get() = Some((thread::current().id(), &raw const removed));
+                drop(queue);
+
+                (node.run)(self.0, run);
+                if !removed.get() {
+                    self.0.as_ref().state.store(DONE, Release);
+                }
+            }
+        }
+    }
 
     struct Inner {
-        data: Mutex<VecDeque<BoxedRecv>>,
+        data: Mutex<VecDeque<Task>>,
         cv: Condvar,
         stopped: AtomicBool,
     }
 
     impl Inner {
-        fn push(&self, r: BoxedRecv) {
+        fn push(&self, task: Task) {
             let mut data = self.data.lock().unwrap();
-            data.push_back(r);
-            std::println!("Runner push");
+            // SAFETY: The node is valid, and we hold the lock.
+            let node = unsafe { task.0.as_ref() };
+            if self.stopped.load(SeqCst) || node.state.load(Relaxed) == CANCELED {
+                task.run(data, false);
+                return;
+            }
+            node.state.store(QUEUED, Relaxed);
+            data.push_back(task);
             self.cv.notify_one();
         }
 
-        fn pop(&self) -> Option<BoxedRecv> {
+        /// Cancels the task if it is still queued, or keeps it from being
+        /// queued.
+        ///
+        /// # Safety
+        ///
+        /// The task must be valid.
+        unsafe fn stop(&self, task: Task) {
+            let mut data = self.data.lock().unwrap();
+            // SAFETY: The task is valid, and we hold the lock.
+            let node = unsafe { task.0.as_ref() };
+            match node.state.load(Relaxed) {
+                QUEUED => {
+                    data.retain(|&queued| queued != task);
+                    task.run(data, false);
+                }
+                IDLE => node.state.store(CANCELED, Relaxed),
+                _ => {}
+            }
+        }
+
+        /// Runs the queued tasks until the loop is stopped.
+        fn run(&self) {
             let mut data = self.data.lock().unwrap();
             while !self.stopped.load(SeqCst) {
-                if let Some(r) = data.pop_front() {
-                    std::println!("Runner pop");
-                    return Some(r);
+                match data.pop_front() {
+                    Some(task) => {
+                        task.run(data, true);
+                        data = self.data.lock().unwrap();
+                    }
+                    None => data = self.cv.wait(data).unwrap(),
                 }
-                data = self.cv.wait(data).unwrap();
             }
-            None
+        }
+
+        /// Cancels the tasks that are still queued.
+        fn cancel_all(&self) {
+            loop {
+                let mut data = self.data.lock().unwrap();
+                let Some(task) = data.pop_front() else {
+                    break;
+                };
+                task.run(data, false);
+            }
+        }
+
+        /// Removes the task from the queue if it is still there, or waits for
+        /// it to finish running otherwise.
+        ///
+        /// # Safety
+        ///
+        /// The task must be valid.
+        unsafe fn remove(&self, task: Task) {
+            let mut data = self.data.lock().unwrap();
+            // SAFETY: The task is valid, and we hold the lock.
+            let node = unsafe { task.0.as_ref() };
+            match node.state.load(Relaxed) {
+                QUEUED => {
+                    data.retain(|&queued| queued != task);
+                    node.state.store(IDLE, Relaxed);
+                }
+                RUNNING => {
+                    // SAFETY: The runner is set before the task runs.
+                    let (runner, removed) = unsafe { *node.runner.get() }.unwrap();
+                    drop(data);
+                    if runner == thread::current().id() {
+                        // Dropped from within the task.
+                        // SAFETY: `removed` lives on the stack of `Task::run`, which is
+                        // still running on this thread.
+                        unsafe { (*removed).set(true) };
+                    } else {
+                        while node.state.load(Acquire) != DONE {
+                            thread::yield_now();
+                        }
+                    }
+                }
+                _ => {}
+            }
         }
     }
 
+    /// A run loop on a dedicated thread.
+    ///
+    /// Dropping the loop joins the thread, and the tasks that are still queued
+    /// complete with stopped signals.
     pub struct Loop {
         inner: Arc<Inner>,
         thread: Option<JoinHandle<()>>,
@@ -70,15 +200,12 @@ mod run_loop {
             let i2 = inner.clone();
             Loop {
                 inner,
-                thread: Some(std::thread::spawn(move || {
-                    iter::from_fn(|| i2.pop()).for_each(|r| r());
-                    std::println!("Runner exit");
-                })),
+                thread: Some(std::thread::spawn(move || i2.run())),
             }
         }
 
-        pub fn arc(&self) -> ArcLoop<'_> {
-            ArcLoop(&self.inner)
+        pub fn scheduler(&self) -> LoopScheduler {
+            LoopScheduler(self.inner.clone())
         }
     }
 
@@ -95,87 +222,161 @@ mod run_loop {
             if let Some(thread) = self.thread.take() {
                 thread.join().unwrap();
             }
+            self.inner.cancel_all();
         }
     }
 
-    pub struct LoopSender<'a>(&'a Inner);
+    /// The scheduler of a [`Loop`].
+    #[derive(Clone)]
+    pub struct LoopScheduler(Arc<Inner>);
 
-    pub struct LoopExec<'a, R> {
-        inner: &'a Inner,
-        recv: R,
+    impl fmt::Debug for LoopScheduler {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_tuple("LoopScheduler")
+                .field(&Arc::as_ptr(&self.0))
+                .finish()
+        }
     }
 
-    impl<'a> Scheduler for &'a Loop {
-        type Sender = LoopSender<'a>;
-
-        fn schedule(self) -> Self::Sender {
-            LoopSender(&self.inner)
+    impl PartialEq for LoopScheduler {
+        fn eq(&self, other: &Self) -> bool {
+            Arc::ptr_eq(&self.0, &other.0)
         }
     }
 
-    impl<'a> Sender for LoopSender<'a> {
-        type Output = ();
-    }
+    impl Eq for LoopScheduler {}
 
-    impl<'a, R> SenderTo<R> for LoopSender<'a>
-    where
-        R: ReceiverFrom<Self> + Send + 'static,
-    {
-        type Execution = LoopExec<'a, R>;
+    pub struct LoopTaskExpr;
 
-        fn connect(self, receiver: R) -> Self::Execution {
-            LoopExec { inner: self.0, recv: receiver }
+    impl SenderExpr for LoopTaskExpr {
+        type Output = ();
+        type Error = Infallible;
+        type Stopped = Yes;
+        type Attrs = SchedulerAttrs<LoopScheduler>;
+        type Data = LoopScheduler;
+        type SubSenders = ();
+
+        fn attrs(sched: &LoopScheduler, _: &()) -> Self::Attrs {
+            SchedulerAttrs(sched.clone())
         }
     }
 
-    impl<'a, R> Execution for LoopExec<'a, R>
-    where
-        R: Receiver<()> + Send + 'static,
-    {
-        fn execute(self) {
-            self.inner.push(Box::new(move || self.recv.receive(())))
+    /// The operation state of [`LoopTask`].
+    /// The stop callback of [`LoopTask`], which cancels the task if it is still
+    /// queued.
+    struct StopTask(LoopScheduler, Task);
+
+    impl OnStop for StopTask {
+        fn on_stop(self) {
+            // SAFETY: The callback is dropped before the node, along with the
+            // operation state.
+            unsafe { self.0.0.stop(self.1) }
         }
     }
 
-    #[derive(Clone, Copy)]
-    pub struct ArcLoop<'a>(&'a Arc<Inner>);
-
-    pub struct ArcLoopSender(Arc<Inner>);
-
-    pub struct ArcLoopExec<R> {
-        inner: Arc<Inner>,
-        recv: R,
+    #[repr(C)]
+    pub struct LoopState<R: GetEnv<Env: QueryStopToken>> {
+        // `node` must be the first field so that it can be cast back to `Self`.
+        node: TaskNode,
+        sched: LoopScheduler,
+        // Set before the task is queued, and taken by its runner.
+        recv: UnsafeCell<Option<R>>,
+        // Registered before the task is queued, and dropped along with the
+        // operation state, since the runner may run from within it.
+        on_stop: EnvStopCallback<EnvOf<R>, StopTask>,
+        _marker: PhantomPinned,
     }
 
-    impl<'a> Scheduler for ArcLoop<'a> {
-        type Sender = ArcLoopSender;
-
-        fn schedule(self) -> Self::Sender {
-            ArcLoopSender(self.0.clone())
+    // SAFETY: The receiver is only accessed by the runner of the task once it is
+    // queued.
+    unsafe impl<R: GetEnv<Env: QueryStopToken + Send> + Send> Send for LoopState<R> {}
+    // SAFETY: Same as above.
+    unsafe impl<R: GetEnv<Env: QueryStopToken + Send> + Send> Sync for LoopState<R> {}
+
+    impl<R: Receiver<(), Env: QueryStopToken>> LoopState<R> {
+        /// # Safety
+        ///
+        /// `node` must point to the `node` field of a valid `Self`, and this
+        /// function must be called at most once for it.
+        unsafe fn run(node: NonNull<TaskNode>, run: bool) {
+            let this = node.cast::<Self>().as_ptr();
+            // SAFETY: The receiver is taken out only once, by the runner.
+            let recv = unsafe { (*(*this).recv.get()).take() };
+            let recv = recv.expect(ONESHOT_COMPLETED);
+            if run {
+                recv.set(());
+            } else {
+                recv.set_stopped();
+            }
         }
     }
 
-    impl Sender for ArcLoopSender {
-        type Output = ();
+    impl<R: GetEnv<Env: QueryStopToken>> Drop for LoopState<R> {
+        fn drop(&mut self) {
+            // The receiver is dropped along with the operation if the task has
+            // not run yet. The stop callback is dropped after this, which is fine
+            // since the node has nothing to drop.
+            let task = Task(NonNull::from_ref(&self.node));
+            // SAFETY: The node is valid until the end of this function.
+            unsafe { self.sched.0.remove(task) };
+        }
     }
 
-    impl<R> SenderTo<R> for ArcLoopSender
+    impl<R> SenderExprTo<R> for LoopTaskExpr
     where
-        R: ReceiverFrom<Self> + Send + 'static,
+        R: Receiver<(), Env: QueryStopToken + Send> + Send,
     {
-        type Execution = ArcLoopExec<R>;
+        type State = LoopState<R>;
+        type ConnectError = Infallible;
+        type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+        fn create_state(sched: LoopScheduler, _: &mut (), recv: R) -> Self::CreateState {
+            init::with(move || LoopState {
+                node: TaskNode {
+                    state: AtomicU8::new(IDLE),
+                    runner: UnsafeCell::new(None),
+                    run: LoopState::<R>::run,
+                },
+                sched,
+                recv: UnsafeCell::new(Some(recv)),
+                on_stop: EnvStopCallback::new(),
+                _marker: PhantomPinned,
+            })
+        }
+
+        fn start(state: Pin<&mut State<Self, R>>, _: Pin<&mut ConnectAllOps<Self, R>>)
+        where
+            State<Self, R>: ConnectAll<Self, R>,
+        {
+            let state = state.state_mut().into_ref().get_ref();
+            let task = Task(NonNull::from_ref(&state.node));
+
+            let stop = StopTask(state.sched.clone(), task);
+            // SAFETY: The task is not queued yet, so we are the only one accessing the
+            // receiver and the stop callback, which is pinned along with the state.
+            unsafe {
+                let recv = (*state.recv.get()).as_ref().expect(ONESHOT_COMPLETED);
+                state.on_stop.register(recv.get_env(), stop);
+            }
+            state.sched.0.push(task);
+        }
 
-        fn connect(self, receiver: R) -> Self::Execution {
-            ArcLoopExec { inner: self.0, recv: receiver }
+        fn complete(_: Pin<&mut State<Self, R>>, value: tsum::Sum<()>) {
+            value.unreachable();
+        }
+
+        fn error(_: Pin<&mut State<Self, R>>, error: tsum::Sum<()>) {
+            error.unreachable();
         }
     }
 
-    impl<R> Execution for ArcLoopExec<R>
-    where
-        R: Receiver<()> + Send + 'static,
-    {
-        fn execute(self) {
-            self.inner.push(Box::new(move || self.recv.receive(())))
+    pub type LoopTask = BasicSender<LoopTaskExpr>;
+
+    impl Scheduler for LoopScheduler {
+        type Task = LoopTask;
+
+        fn schedule(&self) -> LoopTask {
+            BasicSender::new(self.clone(), ())
         }
     }
 }
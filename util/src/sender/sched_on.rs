@@ -1,48 +1,112 @@
-use rxec_core::{Execution, Receiver, ReceiverFrom, Sender, SenderTo};
+use core::{
+    convert::Infallible,
+    marker::{PhantomData, PhantomPinned},
+    pin::Pin,
+    ptr::NonNull,
+};
 
-use super::Scheduler;
+use pin_project::pin_project;
+use placid::{place::DynPlace, prelude::*};
+use rxec_core::{
+    Receiver, Scheduler, Sender, SenderTo,
+    basic::*,
+    completion::{Flag, FlagOr, SenderStopped},
+    env::{EnvOf, GetEnv, GetStopToken, QueryStopToken},
+    stop::StopToken,
+    util::{TryConnectRecv, try_start_in_place},
+};
+use tsum::{Sum, T, t};
 
-pub fn sched_on<S, Sched>(s: S, sched: Sched) -> SchedOn<S, Sched> {
-    SchedOn(s, sched)
-}
+use super::ONESHOT_COMPLETED;
+
+pub struct SchedOnExpr<S, Sched>(PhantomData<(S, Sched)>);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct SchedOn<S, Sched>(S, Sched);
+#[derive(InitPin)]
+#[pin_project]
+pub struct SchedOnState<O, S, R> {
+    #[pin]
+    pinned: PhantomPinned,
+    sender: Option<S>,
+    // `next_op` must come before `recv`, since its receiver refers to it.
+    #[pin]
+    next_op: DynPlace<O>,
+    recv: Option<R>,
+}
 
-impl<S, Sched> Sender for SchedOn<S, Sched>
+impl<S, Sched> SenderExpr for SchedOnExpr<S, Sched>
 where
     S: Sender,
-    Sched: Scheduler,
+    Sched: Scheduler<Task: Sender<Error = Infallible>>,
 {
     type Output = S::Output;
+    type Error = S::Error;
+    type Stopped = FlagOr<SenderStopped<Sched::Task>, S::Stopped>;
+    type Attrs = EnvOf<S>;
+    type Data = S;
+    type SubSenders = T![Sched::Task];
+
+    fn attrs(sender: &S, _: &T![Sched::Task]) -> EnvOf<S> {
+        sender.get_env()
+    }
 }
 
-impl<S, Sched, R> SenderTo<R> for SchedOn<S, Sched>
+impl<S, Sched, R> SenderExprTo<R> for SchedOnExpr<S, Sched>
 where
-    S: SenderTo<R>,
-    R: ReceiverFrom<S>,
-    Sched: Scheduler,
-    Sched::Sender: SenderTo<Recv<S, R>>,
+    S: SenderTo<TryConnectRecv<R>, Operation: Sized, ConnectError: Into<S::Error>>,
+    Sched: Scheduler<Task: Sender<Error = Infallible>>,
+    R: Receiver<S::Output, S::Error> + GetEnv<Env: QueryStopToken>,
 {
-    type Execution = <Sched::Sender as SenderTo<Recv<S, R>>>::Execution;
+    type State = SchedOnState<S::Operation, S, R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
 
-    fn connect(self, receiver: R) -> Self::Execution {
-        self.1.schedule().connect(Recv { sender: self.0, receiver })
+    fn create_state(sender: S, _: &mut Self::SubSenders, recv: R) -> Self::CreateState {
+        init_pin!(SchedOnState {
+            pinned: PhantomPinned,
+            sender: || Some(sender),
+            next_op: DynPlace::new,
+            recv: || Some(recv),
+        })
+    }
+
+    fn complete(state: Pin<&mut State<Self, R>>, _: Sum![()]) {
+        let state = state.state_mut().project();
+        let sender = state.sender.take().expect(ONESHOT_COMPLETED);
+        let recv = state.recv.as_ref().expect(ONESHOT_COMPLETED);
+        // The sender is not started if a stop has been requested in the meantime,
+        // as long as the completions allow for it.
+        if <Self::Stopped as Flag>::VALUE && recv.get_env().query(GetStopToken).stop_requested() {
+            state.recv.take().expect(ONESHOT_COMPLETED).set_stopped();
+            return;
+        }
+        // SAFETY: `recv` lives after the operation in the state, which is started
+        // only once here and not forgotten after started since it requires outer
+        // `OperationState::start`.
+        unsafe { try_start_in_place(sender, state.next_op, NonNull::from_mut(state.recv)) }
+    }
+
+    fn error(_: Pin<&mut State<Self, R>>, error: Sum![Infallible]) {
+        match error.into_inner() {}
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Recv<S, R> {
-    sender: S,
-    receiver: R,
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        let state = state.state_mut().project();
+        if state.sender.take().is_some() {
+            state.recv.take().expect(ONESHOT_COMPLETED).set_stopped();
+        }
+    }
 }
 
-impl<S, R> Receiver<()> for Recv<S, R>
+pub type SchedOn<S, Sched> = BasicSender<SchedOnExpr<S, Sched>>;
+
+/// Starts the sender on the scheduler.
+///
+/// The sender is connected on the scheduler, and completes with the connect
+/// error converted into its error type if the connection fails.
+pub fn sched_on<S, Sched>(s: S, sched: Sched) -> SchedOn<S, Sched>
 where
-    S: SenderTo<R>,
-    R: ReceiverFrom<S>,
+    S: Sender,
+    Sched: Scheduler<Task: Sender<Error = Infallible>>,
 {
-    fn receive(self, _: ()) {
-        self.sender.connect(self.receiver).execute();
-    }
+    BasicSender::new(s, t![sched.schedule()])
 }
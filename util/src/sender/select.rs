@@ -1,83 +1,90 @@
-use alloc::sync::Arc;
-use core::marker::PhantomData;
+use core::{convert::Infallible, marker::PhantomData, pin::Pin};
 
 use either::Either::{self, Left, Right};
-use rxec_core::{Receiver, ReceiverFrom, Sender, SenderTo};
-use spin::Mutex;
+use placid::prelude::*;
+use rxec_core::{
+    Receiver, Sender, basic::*, completion::FlagAnd, env::EmptyEnv, list::split_first,
+};
+use tsum::{Sum, T, t};
 
 pub fn select<S1, S2>(s1: S1, s2: S2) -> Select<S1, S2>
 where
     S1: Sender,
     S2: Sender,
 {
-    Select(s1, s2)
+    BasicSender::new((), t![s1, s2])
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Select<S1, S2>(S1, S2);
+pub struct SelectExpr<S1, S2>(PhantomData<(S1, S2)>);
 
-impl<S1, S2> Sender for Select<S1, S2>
+impl<S1, S2> SenderExpr for SelectExpr<S1, S2>
 where
     S1: Sender,
     S2: Sender,
 {
     type Output = Either<S1::Output, S2::Output>;
-}
+    type Error = Either<S1::Error, S2::Error>;
+    // Stopped only if both of the senders are stopped.
+    type Stopped = FlagAnd<S1::Stopped, S2::Stopped>;
+    type Attrs = EmptyEnv;
+    type Data = ();
+    type SubSenders = T![S1, S2];
 
-impl<S1, S2, R> SenderTo<R> for Select<S1, S2>
-where
-    S1: SenderTo<Recv<S1, S2, R, false>>,
-    S2: SenderTo<Recv<S1, S2, R, true>>,
-    R: ReceiverFrom<Select<S1, S2>>,
-{
-    type Execution = (S1::Execution, S2::Execution);
-
-    fn connect(self, receiver: R) -> Self::Execution {
-        let shared = Arc::new(Mutex::new(Some(receiver)));
-        let e1 = self.0.connect(Recv {
-            shared: shared.clone(),
-            marker: PhantomData,
-        });
-        let e2 = self.1.connect(Recv { shared, marker: PhantomData });
-        (e1, e2)
+    fn attrs(_: &(), _: &T![S1, S2]) -> EmptyEnv {
+        EmptyEnv
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Recv<S1, S2, R, const EITHER: bool>
-where
-    S1: SenderTo<Recv<S1, S2, R, false>>,
-    S2: SenderTo<Recv<S1, S2, R, true>>,
-    R: ReceiverFrom<Select<S1, S2>>,
-{
-    shared: Arc<Mutex<Option<R>>>,
-    marker: PhantomData<fn(S1::Output, S2::Output)>,
+pub struct SelectState<R> {
+    recv: OnceSlot<R>,
+    stopped: Countdown,
 }
 
-impl<S1, S2, R> Receiver<S1::Output> for Recv<S1, S2, R, false>
+impl<S1, S2, R> SenderExprTo<R> for SelectExpr<S1, S2>
 where
-    S1: SenderTo<Recv<S1, S2, R, false>>,
-    S2: SenderTo<Recv<S1, S2, R, true>>,
-    R: ReceiverFrom<Select<S1, S2>>,
+    S1: Sender,
+    S2: Sender,
+    R: Receiver<Self::Output, Self::Error>,
 {
-    fn receive(self, value: S1::Output) {
-        let Some(receiver) = self.shared.lock().take() else {
-            return;
+    type State = SelectState<R>;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
+
+    fn create_state(_: (), _: &mut T![S1, S2], recv: R) -> Self::CreateState {
+        init::with(move || SelectState {
+            recv: OnceSlot::full(recv),
+            stopped: Countdown::new(2),
+        })
+    }
+
+    fn complete(state: Pin<&State<Self, R>>, value: Sum![S1::Output, S2::Output]) {
+        let value = match split_first(value) {
+            Ok(value) => Left(value),
+            Err(rest) => Right(rest.into_inner()),
         };
-        receiver.receive(Left(value));
+        if let Some(recv) = state.state().get_ref().recv.take() {
+            recv.set(value);
+        }
     }
-}
 
-impl<S1, S2, R> Receiver<S2::Output> for Recv<S1, S2, R, true>
-where
-    S1: SenderTo<Recv<S1, S2, R, false>>,
-    S2: SenderTo<Recv<S1, S2, R, true>>,
-    R: ReceiverFrom<Select<S1, S2>>,
-{
-    fn receive(self, value: S2::Output) {
-        let Some(receiver) = self.shared.lock().take() else {
-            return;
+    fn error(state: Pin<&State<Self, R>>, error: Sum![S1::Error, S2::Error]) {
+        let error = match split_first(error) {
+            Ok(error) => Left(error),
+            Err(rest) => Right(rest.into_inner()),
         };
-        receiver.receive(Right(value));
+        if let Some(recv) = state.state().get_ref().recv.take() {
+            recv.set_error(error);
+        }
+    }
+
+    fn stop(state: Pin<&State<Self, R>>) {
+        let state = state.state().get_ref();
+        if state.stopped.arrive()
+            && let Some(recv) = state.recv.take()
+        {
+            recv.set_stopped();
+        }
     }
 }
+
+pub type Select<S1, S2> = BasicSender<SelectExpr<S1, S2>>;
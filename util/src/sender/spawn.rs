@@ -0,0 +1,21 @@
+use core::convert::Infallible;
+
+use rxec_core::{
+    Scheduler, Sender, SenderTo,
+    util::{DetachedRecv, start_detached},
+};
+
+use super::{SchedOn, sched_on};
+
+/// Starts the sender detached on the scheduler, as with [`start_detached`].
+///
+/// If the scheduler stops the task, e.g. because it is shutting down, the
+/// sender is dropped without running.
+pub fn spawn<Sched, S>(sched: Sched, sender: S)
+where
+    Sched: Scheduler<Task: Sender<Error = Infallible>>,
+    S: Sender<Error = Infallible>,
+    SchedOn<S, Sched>: SenderTo<DetachedRecv, ConnectError = Infallible, Operation: Send + 'static>,
+{
+    let Ok(()) = start_detached(sched_on(sender, sched));
+}
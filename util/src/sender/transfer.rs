@@ -1,65 +1,190 @@
-use rxec_core::{Execution, Receiver, Sender, SenderTo};
+use core::{
+    convert::Infallible,
+    marker::{PhantomData, PhantomPinned},
+    pin::Pin,
+    ptr::NonNull,
+};
 
-use super::Scheduler;
+use pin_project::pin_project;
+use placid::{place::DynPlace, prelude::*};
+use rxec_core::{
+    OperationState, Receiver, Scheduler, Sender, SenderTo,
+    basic::*,
+    completion::{Completion, FlagOr, SenderStopped},
+    env::{EnvOf, GetEnv, SchedulerAttrs},
+    util::TryConnectRecv,
+};
+use tsum::{Sum, T, t};
 
-pub fn transfer<S, Sched>(s: S, sched: Sched) -> Transfer<S, Sched> {
-    Transfer(s, sched)
-}
+use super::ONESHOT_COMPLETED;
+
+pub struct TransferExpr<S, Sched>(PhantomData<(S, Sched)>);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Transfer<S, Sched>(S, Sched);
+#[derive(InitPin)]
+#[pin_project]
+pub struct TransferState<O, Sched, R> {
+    #[pin]
+    pinned: PhantomPinned,
+    sched: Option<Sched>,
+    // `next_op` must come before `remote`, since its receiver refers to it.
+    #[pin]
+    next_op: DynPlace<O>,
+    remote: Option<R>,
+}
 
-impl<S, Sched> Sender for Transfer<S, Sched>
+impl<S, Sched> SenderExpr for TransferExpr<S, Sched>
 where
     S: Sender,
-    Sched: Scheduler,
+    Sched: Scheduler<Task: Sender<Error = Infallible>> + Clone,
 {
     type Output = S::Output;
+    type Error = S::Error;
+    type Stopped = FlagOr<S::Stopped, SenderStopped<Sched::Task>>;
+    type Attrs = SchedulerAttrs<Sched>;
+    type Data = Sched;
+    type SubSenders = T![S];
+
+    fn attrs(sched: &Sched, _: &T![S]) -> SchedulerAttrs<Sched> {
+        SchedulerAttrs(sched.clone())
+    }
 }
 
-impl<S, Sched, R> SenderTo<R> for Transfer<S, Sched>
+impl<S, Sched, R> SenderExprTo<R> for TransferExpr<S, Sched>
 where
-    S: SenderTo<Local<R, Sched>>,
-    R: Receiver<S::Output>,
-    Sched: Scheduler,
-    Sched::Sender: SenderTo<Remote<R, S::Output>>,
+    S: Sender,
+    Sched: Scheduler<
+            Task: SenderTo<
+                TryConnectRecv<Remote<R, S::Output, S::Error>>,
+                Operation: Sized,
+                Error = Infallible,
+                ConnectError: Into<S::Error>,
+            >,
+        > + Clone,
+    R: Receiver<S::Output, S::Error>,
 {
-    type Execution = S::Execution;
+    type State = TransferState<
+        <Sched::Task as SenderTo<TryConnectRecv<Remote<R, S::Output, S::Error>>>>::Operation,
+        Sched,
+        Remote<R, S::Output, S::Error>,
+    >;
+    type ConnectError = Infallible;
+    type CreateState = impl InitPin<Self::State, Error = Self::ConnectError>;
 
-    fn connect(self, receiver: R) -> Self::Execution {
-        self.0.connect(Local { receiver, sched: self.1 })
+    fn create_state(sched: Sched, _: &mut T![S], recv: R) -> Self::CreateState {
+        init_pin!(TransferState {
+            pinned: PhantomPinned,
+            sched: || Some(sched),
+            next_op: DynPlace::new,
+            remote: || { Some(Remote { receiver: recv, completion: None }) },
+        })
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Local<R, Sched> {
-    receiver: R,
-    sched: Sched,
+    fn complete(state: Pin<&mut State<Self, R>>, value: Sum![S::Output]) {
+        state
+            .state_mut()
+            .schedule(Completion::Value(value.into_inner()));
+    }
+
+    fn error(state: Pin<&mut State<Self, R>>, error: Sum![S::Error]) {
+        state
+            .state_mut()
+            .schedule(Completion::Error(error.into_inner()));
+    }
+
+    fn stop(state: Pin<&mut State<Self, R>>) {
+        let state = state.state_mut();
+        if state.sched.is_some() {
+            state.schedule(Completion::Stopped);
+        }
+    }
 }
 
-impl<R, Sched, T> Receiver<T> for Local<R, Sched>
+impl<O, Sched, R, T, E> TransferState<O, Sched, Remote<R, T, E>>
 where
-    R: Receiver<T>,
-    Sched: Scheduler,
-    Sched::Sender: SenderTo<Remote<R, T>>,
+    Sched: Scheduler<
+        Task: SenderTo<
+            TryConnectRecv<Remote<R, T, E>>,
+            Operation = O,
+            Error = Infallible,
+            ConnectError: Into<E>,
+        >,
+    >,
+    R: Receiver<T, E>,
 {
-    fn receive(self, value: T) {
-        let remote = Remote { receiver: self.receiver, value };
-        self.sched.schedule().connect(remote).execute()
+    /// Delivers the completion of the sender from a task on the scheduler.
+    fn schedule(self: Pin<&mut Self>, completion: Completion<T, E>) {
+        let this = self.project();
+        let sched = this.sched.take().expect(ONESHOT_COMPLETED);
+        let remote = this.remote.as_mut().expect(ONESHOT_COMPLETED);
+        remote.completion = Some(completion);
+        let recv = NonNull::from_mut(this.remote);
+        // SAFETY: `remote` lives after the task in the state, and is only accessed
+        // through it from now on.
+        let next_op = sched
+            .schedule()
+            .connect(unsafe { TryConnectRecv::new(recv) });
+
+        match this.next_op.try_insert_pin(next_op) {
+            // SAFETY: The operation is started only once here, and the state is not
+            // forgotten after started since it requires outer `OperationState::start`.
+            Ok(next_op) => unsafe { next_op.start_by_ref() },
+            Err(err) => {
+                // The scheduler cannot run the task, so its connect error is delivered
+                // inline instead of the completion.
+                // SAFETY: The receiver of the failed connection has been dropped, and
+                // left the remote in place.
+                let remote = unsafe { (*recv.as_ptr()).take() };
+                let remote = remote.expect(ONESHOT_COMPLETED);
+                remote.receiver.set_error(err.error.into());
+            }
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-struct Remote<R, T> {
+/// Delivers the completion on the scheduler it is transferred to.
+pub struct Remote<R, T, E> {
     receiver: R,
-    value: T,
+    completion: Option<Completion<T, E>>,
 }
 
-impl<R, T> Receiver<()> for Remote<R, T>
+impl<R: GetEnv, T, E> GetEnv for Remote<R, T, E> {
+    type Env = EnvOf<R>;
+
+    fn get_env(&self) -> EnvOf<R> {
+        self.receiver.get_env()
+    }
+}
+
+impl<R, T, E> Receiver<()> for Remote<R, T, E>
 where
-    R: Receiver<T>,
+    R: Receiver<T, E>,
 {
-    fn receive(self, _: ()) {
-        self.receiver.receive(self.value)
+    fn set(self, _: ()) {
+        let completion = self.completion.expect(ONESHOT_COMPLETED);
+        completion.deliver(self.receiver);
+    }
+
+    fn set_error(self, error: Infallible) {
+        match error {}
+    }
+
+    fn set_stopped(self) {
+        self.receiver.set_stopped()
     }
 }
+
+pub type Transfer<S, Sched> = BasicSender<TransferExpr<S, Sched>>;
+
+/// Completes the sender on the scheduler, with its value, error or stopped
+/// signal alike.
+///
+/// The task of the scheduler is connected once the sender completes. If the
+/// connection fails, the connect error is delivered as an error right away,
+/// which is the only completion that does not happen on the scheduler.
+pub fn transfer<S, Sched>(s: S, sched: Sched) -> Transfer<S, Sched>
+where
+    S: Sender,
+    Sched: Scheduler<Task: Sender<Error = Infallible>> + Clone,
+{
+    BasicSender::new(sched, t![s])
+}